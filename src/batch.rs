@@ -0,0 +1,613 @@
+use crate::extract::Extractor;
+use crate::{Options, epub, parse_input_url};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use tracing::{debug, info, warn};
+
+/// Skips articles published before a cutoff date
+#[derive(Clone, Debug)]
+pub struct DateFilter {
+    pub since: DateTime<Utc>,
+    /// Whether articles without a detectable publication date are converted
+    pub include_undated: bool,
+}
+
+impl DateFilter {
+    pub fn accepts(&self, date_published: Option<DateTime<Utc>>) -> bool {
+        match date_published {
+            Some(date) => date >= self.since,
+            None => self.include_undated,
+        }
+    }
+}
+
+/// Options that apply across a batch of URLs
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    pub since: Option<DateFilter>,
+    /// Number of URLs converted concurrently
+    pub jobs: usize,
+    /// JSON file recording each URL's outcome, so an interrupted batch can be resumed
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            since: None,
+            jobs: 1,
+            state_file: None,
+        }
+    }
+}
+
+/// What happened to each URL in a batch
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub converted: Vec<(String, PathBuf)>,
+    /// URLs the state file records as converted by an earlier run
+    pub already_converted: Vec<(String, PathBuf)>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+/// The persisted outcome of every URL a batch has attempted
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchState {
+    pub urls: BTreeMap<String, UrlState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UrlState {
+    pub status: UrlStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Only `Converted` URLs are skipped on the next run; skipped and failed ones are retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlStatus {
+    Converted,
+    Skipped,
+    Failed,
+}
+
+impl BatchState {
+    /// Reads a state file. A missing file is a fresh start; an unreadable or
+    /// corrupt one is renamed to `<state>.bad`, so the batch saving its progress
+    /// does not overwrite it, and reported.
+    pub fn load(path: &Path) -> Self {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!(path = %path.display(), "No state file yet; starting a fresh batch.");
+                return Self::default();
+            }
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read state file.");
+                set_aside(path);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            warn!(path = %path.display(), error = %e, "State file is corrupt.");
+            set_aside(path);
+            Self::default()
+        })
+    }
+
+    /// Writes the state to a temporary file and renames it into place, so an
+    /// interruption never leaves a half-written state file behind
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize batch state")?;
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+        fs::write(&temp_path, json).context(format!(
+            "Failed to write state file: {}",
+            temp_path.display()
+        ))?;
+        fs::rename(&temp_path, path)
+            .context(format!("Failed to replace state file: {}", path.display()))
+    }
+
+    fn converted_output(&self, url_str: &str) -> Option<&PathBuf> {
+        self.urls
+            .get(url_str)
+            .filter(|entry| entry.status == UrlStatus::Converted)
+            .and_then(|entry| entry.output.as_ref())
+    }
+
+    fn record(&mut self, url_str: &str, outcome: &Result<Outcome>) {
+        let entry = match outcome {
+            Ok(Outcome::Converted(path)) => UrlState {
+                status: UrlStatus::Converted,
+                output: Some(path.clone()),
+                error: None,
+            },
+            Ok(Outcome::Skipped) => UrlState {
+                status: UrlStatus::Skipped,
+                output: None,
+                error: None,
+            },
+            Err(e) => UrlState {
+                status: UrlStatus::Failed,
+                output: None,
+                error: Some(format!("{e:#}")),
+            },
+        };
+        self.urls.insert(url_str.to_string(), entry);
+    }
+}
+
+// Keeps a state file that could not be loaded as `<state>.bad` before a fresh batch starts
+fn set_aside(path: &Path) {
+    let mut bad_name = path.file_name().unwrap_or_default().to_os_string();
+    bad_name.push(".bad");
+    let bad_path = path.with_file_name(bad_name);
+    match fs::rename(path, &bad_path) {
+        Ok(()) => warn!(
+            path = %bad_path.display(),
+            "Moved the unusable state file aside; starting a fresh batch."
+        ),
+        Err(e) => warn!(
+            path = %path.display(),
+            error = %e,
+            "Failed to move the unusable state file aside; starting a fresh batch that will replace it."
+        ),
+    }
+}
+
+enum Outcome {
+    Converted(PathBuf),
+    Skipped,
+}
+
+/// Converts each URL with a single extractor, so the HTTP client and its
+/// connection pool are shared by the whole batch. Up to `jobs` URLs are converted
+/// at once. A failing URL is recorded in the summary and does not stop the rest
+/// of the batch. With a state file, URLs converted by an earlier run are skipped
+/// and each outcome is saved as soon as it is known.
+///
+/// With several URLs, `output_path` must name a directory; it is created when it
+/// ends with a path separator. Otherwise an error is returned before anything is fetched.
+pub fn run(
+    urls: &[String],
+    output_path: Option<&PathBuf>,
+    options: &Options,
+    batch_options: &BatchOptions,
+) -> Result<BatchSummary> {
+    if urls.len() > 1
+        && let Some(output_path) = output_path
+    {
+        prepare_output_dir(output_path)?;
+    }
+
+    let state = batch_options
+        .state_file
+        .as_deref()
+        .map(|path| (path, Mutex::new(BatchState::load(path))));
+
+    let mut summary = BatchSummary::default();
+    let mut pending = Vec::new();
+    for (index, url_str) in urls.iter().enumerate() {
+        let previous_output = state.as_ref().and_then(|(_, state)| {
+            let state = state.lock().unwrap();
+            state.converted_output(url_str).cloned()
+        });
+        match previous_output {
+            Some(path) => {
+                info!(
+                    url = url_str,
+                    "Already converted in an earlier run; skipping."
+                );
+                summary.already_converted.push((url_str.clone(), path));
+            }
+            None => pending.push(index),
+        }
+    }
+
+    let extractor = options.extractor();
+    let convert = |url_str: &String| {
+        info!(url = url_str, "Processing URL");
+        let outcome = convert_one(url_str, &extractor, output_path, options, batch_options);
+        if let Some((path, ref state)) = state {
+            let mut state = state.lock().unwrap();
+            state.record(url_str, &outcome);
+            if let Err(e) = state.save(path) {
+                warn!(error = %e, "Failed to save batch state");
+            }
+        }
+        outcome
+    };
+
+    let workers = batch_options.jobs.clamp(1, pending.len().max(1));
+    let mut outcomes: Vec<(usize, Result<Outcome>)> = if workers == 1 {
+        pending
+            .iter()
+            .map(|&index| (index, convert(&urls[index])))
+            .collect()
+    } else {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let (next, pending, convert) = (&next, &pending, &convert);
+                scope.spawn(move || {
+                    while let Some(&index) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        // The receiver outlives the scope, so sending cannot fail
+                        let _ = sender.send((index, convert(&urls[index])));
+                    }
+                });
+            }
+        });
+        drop(sender);
+        receiver.into_iter().collect()
+    };
+    // Report in input order regardless of completion order
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    for (index, outcome) in outcomes {
+        let url_str = urls[index].clone();
+        match outcome {
+            Ok(Outcome::Converted(path)) => summary.converted.push((url_str, path)),
+            Ok(Outcome::Skipped) => summary.skipped.push(url_str),
+            Err(e) => {
+                warn!(url = url_str, error = %e, "Failed to convert URL");
+                summary.failed.push((url_str, e));
+            }
+        }
+    }
+    Ok(summary)
+}
+
+// Several articles need a directory to go into; a file path would make every
+// article after the first a numbered copy of it
+fn prepare_output_dir(output_path: &Path) -> Result<()> {
+    if output_path.is_dir() {
+        return Ok(());
+    }
+    let names_directory = output_path
+        .as_os_str()
+        .to_string_lossy()
+        .ends_with(std::path::is_separator);
+    if names_directory && !output_path.exists() {
+        return fs::create_dir_all(output_path).context(format!(
+            "Failed to create output directory: {}",
+            output_path.display()
+        ));
+    }
+    anyhow::bail!(
+        "--output must be a directory when converting several URLs: {} (end it with '{}' to create it)",
+        output_path.display(),
+        std::path::MAIN_SEPARATOR
+    )
+}
+
+fn convert_one(
+    url_str: &str,
+    extractor: &Extractor,
+    output_path: Option<&PathBuf>,
+    options: &Options,
+    batch_options: &BatchOptions,
+) -> Result<Outcome> {
+    let url = parse_input_url(url_str)?;
+    let extracted_content = extractor.process(&url)?;
+
+    if let Some(ref filter) = batch_options.since
+        && !filter.accepts(extracted_content.date_published)
+    {
+        match extracted_content.date_published {
+            Some(date) => info!(
+                url = url_str,
+                published = %date,
+                since = %filter.since,
+                "Skipping article published before the cutoff."
+            ),
+            None => info!(url = url_str, "Skipping article with no publication date."),
+        }
+        return Ok(Outcome::Skipped);
+    }
+
+    let path = epub::create_epub_with_options(&extracted_content, output_path, &options.epub)?;
+    Ok(Outcome::Converted(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    fn date(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn article_page(title: &str, published: &str) -> String {
+        let paragraph = "<p>Feed archiving jobs revisit the same reading list periodically, so older entries should be skipped quickly.</p>";
+        format!(
+            r#"<html><head><title>{title}</title><meta property="article:published_time" content="{published}"></head><body><article><h1>{title}</h1>{}</article></body></html>"#,
+            paragraph.repeat(5)
+        )
+    }
+
+    #[test]
+    fn test_date_filter_accepts() {
+        let filter = DateFilter {
+            since: date("2024-02-01T00:00:00Z"),
+            include_undated: false,
+        };
+        assert!(filter.accepts(Some(date("2024-02-01T00:00:00Z"))));
+        assert!(!filter.accepts(Some(date("2024-01-31T23:59:59Z"))));
+        assert!(!filter.accepts(None));
+        assert!(
+            DateFilter {
+                include_undated: true,
+                ..filter
+            }
+            .accepts(None)
+        );
+    }
+
+    #[test]
+    fn test_since_skips_older_articles() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/old")
+            .with_header("content-type", "text/html")
+            .with_body(article_page("Old News", "2024-01-10T09:00:00Z"))
+            .create();
+        server
+            .mock("GET", "/new")
+            .with_header("content-type", "text/html")
+            .with_body(article_page("Fresh News", "2024-03-10T09:00:00Z"))
+            .create();
+        let urls = vec![
+            format!("{}/old", server.url()),
+            format!("{}/new", server.url()),
+        ];
+        let output_dir = tempfile::tempdir().unwrap();
+        let batch_options = BatchOptions {
+            since: Some(DateFilter {
+                since: date("2024-02-01T00:00:00Z"),
+                include_undated: true,
+            }),
+            ..Default::default()
+        };
+
+        let summary = run(
+            &urls,
+            Some(&output_dir.path().to_path_buf()),
+            &Options::default(),
+            &batch_options,
+        )
+        .unwrap();
+
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(summary.skipped, vec![urls[0].clone()]);
+        assert_eq!(summary.converted.len(), 1);
+        assert_eq!(summary.converted[0].0, urls[1]);
+        assert!(summary.converted[0].1.ends_with("Fresh News.epub"));
+    }
+
+    // A keep-alive HTTP server that serves an article for any path and counts the
+    // TCP connections it accepts. Returns the base URL and the connection counter.
+    fn start_counting_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                            break;
+                        }
+                        // Skip headers; GET requests carry no body
+                        let mut line = String::new();
+                        while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                            line.clear();
+                        }
+                        let path = request_line.split(' ').nth(1).unwrap_or("/");
+                        let body = article_page(&format!("Article {path}"), "2024-03-10T09:00:00Z");
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{body}",
+                            body.len()
+                        );
+                        if stream.write_all(response.as_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (base, connections)
+    }
+
+    #[test]
+    fn test_batch_reuses_one_client() {
+        let (base, connections) = start_counting_server();
+        let urls = vec![format!("{base}/one"), format!("{base}/two")];
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let summary = run(
+            &urls,
+            Some(&output_dir.path().to_path_buf()),
+            &Options::default(),
+            &BatchOptions::default(),
+        )
+        .unwrap();
+
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(summary.converted.len(), 2);
+        // Both articles were fetched over the pooled connection of a single client
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_concurrent_batch_keeps_input_order() {
+        let (base, _) = start_counting_server();
+        let urls: Vec<String> = (1..=4).map(|n| format!("{base}/{n}")).collect();
+        let output_dir = tempfile::tempdir().unwrap();
+        let batch_options = BatchOptions {
+            jobs: 3,
+            ..Default::default()
+        };
+
+        let summary = run(
+            &urls,
+            Some(&output_dir.path().to_path_buf()),
+            &Options::default(),
+            &batch_options,
+        )
+        .unwrap();
+
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        let converted_urls: Vec<&String> = summary.converted.iter().map(|(url, _)| url).collect();
+        assert_eq!(converted_urls, urls.iter().collect::<Vec<_>>());
+        let paths: HashSet<&PathBuf> = summary.converted.iter().map(|(_, path)| path).collect();
+        assert_eq!(paths.len(), 4);
+    }
+
+    #[test]
+    fn test_several_urls_need_an_output_directory() {
+        let (base, connections) = start_counting_server();
+        let urls = vec![format!("{base}/one"), format!("{base}/two")];
+        let dir = tempfile::tempdir().unwrap();
+
+        let missing_dir = dir.path().join("books");
+        assert!(
+            run(
+                &urls,
+                Some(&missing_dir),
+                &Options::default(),
+                &BatchOptions::default()
+            )
+            .is_err()
+        );
+        let file = dir.path().join("out.epub");
+        assert!(
+            run(
+                &urls,
+                Some(&file),
+                &Options::default(),
+                &BatchOptions::default()
+            )
+            .is_err()
+        );
+        assert!(!file.exists());
+        // Nothing was fetched for the rejected outputs
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+        let new_dir = PathBuf::from(format!(
+            "{}{}",
+            missing_dir.display(),
+            std::path::MAIN_SEPARATOR
+        ));
+        let summary = run(
+            &urls,
+            Some(&new_dir),
+            &Options::default(),
+            &BatchOptions::default(),
+        )
+        .unwrap();
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert!(missing_dir.is_dir());
+        assert!(
+            summary
+                .converted
+                .iter()
+                .all(|(_, path)| path.starts_with(&missing_dir))
+        );
+    }
+
+    #[test]
+    fn test_state_file_resumes_batch() {
+        let mut server = mockito::Server::new();
+        let article = server
+            .mock("GET", "/kept")
+            .with_header("content-type", "text/html")
+            .with_body(article_page("Kept", "2024-03-10T09:00:00Z"))
+            .expect(1)
+            .create();
+        let urls = vec![
+            format!("{}/kept", server.url()),
+            "http://127.0.0.1:1/unreachable".to_string(),
+        ];
+        let output_dir = tempfile::tempdir().unwrap();
+        let state_path = output_dir.path().join("state.json");
+        let batch_options = BatchOptions {
+            state_file: Some(state_path.clone()),
+            ..Default::default()
+        };
+        let output = output_dir.path().to_path_buf();
+
+        let first = run(&urls, Some(&output), &Options::default(), &batch_options).unwrap();
+        assert_eq!(first.converted.len(), 1);
+        assert_eq!(first.failed.len(), 1);
+
+        let state = BatchState::load(&state_path);
+        assert_eq!(state.urls[&urls[0]].status, UrlStatus::Converted);
+        assert_eq!(state.urls[&urls[1]].status, UrlStatus::Failed);
+        assert!(state.urls[&urls[1]].error.is_some());
+
+        // The converted URL is not fetched again; the failed one is retried
+        let second = run(&urls, Some(&output), &Options::default(), &batch_options).unwrap();
+        assert_eq!(
+            second.already_converted,
+            vec![(urls[0].clone(), first.converted[0].1.clone())]
+        );
+        assert!(second.converted.is_empty());
+        assert_eq!(second.failed.len(), 1);
+        article.assert();
+    }
+
+    #[test]
+    fn test_corrupt_state_file_is_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state.json");
+        fs::write(&state_path, "{ not json").unwrap();
+        assert!(BatchState::load(&state_path).urls.is_empty());
+        // The corrupt file is kept aside instead of being overwritten later
+        assert!(!state_path.exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("state.json.bad")).unwrap(),
+            "{ not json"
+        );
+        assert!(
+            BatchState::load(&dir.path().join("missing.json"))
+                .urls
+                .is_empty()
+        );
+
+        let mut state = BatchState::default();
+        state.record("https://example.com/a", &Ok(Outcome::Skipped));
+        state.save(&state_path).unwrap();
+        assert_eq!(
+            BatchState::load(&state_path).urls["https://example.com/a"].status,
+            UrlStatus::Skipped
+        );
+        assert!(!dir.path().join("state.json.tmp").exists());
+    }
+}
@@ -1,18 +1,137 @@
-use clap::Parser;
+use crate::epub::IdentifierScheme;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// URL of the website to convert to EPUB
-    #[arg(short, long)]
-    pub url: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// URL of the website to convert to EPUB; repeat to convert several in one run
+    #[arg(short, long, required = true)]
+    pub url: Vec<String>,
 
-    /// Output file path (default: website_title.epub)
+    /// Output file path, or a directory when converting several URLs; a directory
+    /// ending in `/` is created if missing (default: website_title.epub)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
+
+    /// Inline images into the article as data: URIs instead of separate files.
+    /// Intended for single-file HTML output; not recommended for EPUB, as it
+    /// considerably increases the file size.
+    #[arg(long)]
+    pub flatten_images: bool,
+
+    /// Skip the generated cover page; the article becomes the first content
+    #[arg(long)]
+    pub no_cover: bool,
+
+    /// Split the article into chapters at each heading of this level (h1-h6)
+    #[arg(long, value_name = "LEVEL", value_parser = parse_heading_level)]
+    pub split_by_heading: Option<u8>,
+
+    /// Only download images from this host or its subdomains; repeat for several
+    #[arg(long, value_name = "HOST")]
+    pub image_allow_host: Vec<String>,
+
+    /// Never download images from this host or its subdomains; repeat for several.
+    /// Takes precedence over --image-allow-host
+    #[arg(long, value_name = "HOST")]
+    pub image_deny_host: Vec<String>,
+
+    /// Class, id or role word marking overlays (cookie banners, paywalls) to remove
+    /// before extraction; repeat to give several. Replaces the default list
+    /// (cookie, consent, paywall, gdpr, modal)
+    #[arg(long, value_name = "PATTERN", conflicts_with = "keep_overlays")]
+    pub overlay_pattern: Vec<String>,
+
+    /// Do not remove cookie-consent, paywall or modal overlays before extraction
+    #[arg(long)]
+    pub keep_overlays: bool,
+
+    /// Remove a trailing " - Site" or " | Site" from the title when it matches the
+    /// site's name (og:site_name) or domain
+    #[arg(long)]
+    pub title_suffix_strip: bool,
+
+    /// How to choose the book identifier: the article URL, a random UUID, or a UUID
+    /// derived from the URL so re-archiving an article keeps its identifier
+    #[arg(long, value_enum, value_name = "SCHEME", default_value_t = IdentifierScheme::Url)]
+    pub identifier: IdentifierScheme,
+
+    /// Write the fetched HTML and the extracted article HTML to this directory for debugging
+    #[arg(long, value_name = "DIR")]
+    pub dump_raw_html: Option<PathBuf>,
+
+    /// How many times to retry a request the server throttles (429, or 503 with Retry-After)
+    #[arg(long, default_value_t = 3)]
+    pub max_retries: u32,
+
+    /// Longest Retry-After wait to honor, in seconds
+    #[arg(long, default_value_t = 60)]
+    pub max_retry_wait: u64,
+
+    /// Maximum number of HTTP redirects to follow (0 disables redirects)
+    #[arg(long, default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Maximum number of HTML meta-refresh redirects to follow (0 disables them)
+    #[arg(long, default_value_t = 5)]
+    pub max_meta_refreshes: usize,
+
+    /// Skip articles published before this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_name = "DATE", value_parser = parse_since)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// With --since, also skip articles that have no publication date
+    #[arg(long, requires = "since")]
+    pub exclude_undated: bool,
+
+    /// Number of URLs to convert concurrently
+    #[arg(short, long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    /// JSON file recording each URL's outcome. URLs it lists as converted are skipped,
+    /// so an interrupted batch can be resumed by re-running the same command
+    #[arg(long, value_name = "FILE")]
+    pub state_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a local HTTP server that converts `GET /convert?url=...` requests to EPUBs
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
 }
 
 pub fn parse_args() -> Args {
     Args::parse()
 }
+
+/// Accepts a heading level written as `h2` or `2`
+fn parse_heading_level(value: &str) -> Result<u8, String> {
+    let digits = value.strip_prefix(['h', 'H']).unwrap_or(value);
+    match digits.parse::<u8>() {
+        Ok(level @ 1..=6) => Ok(level),
+        _ => Err(format!("'{value}' is not a heading level (expected h1-h6)")),
+    }
+}
+
+/// Accepts a cutoff written as `2024-05-01` (midnight UTC) or a full RFC 3339 timestamp
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| format!("'{value}' is not a date (expected YYYY-MM-DD or RFC 3339)"))
+}
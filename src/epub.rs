@@ -1,10 +1,13 @@
 use anyhow::{Context, Result, anyhow};
 use chrono::Utc;
+use dom_query::Document as DomDocument;
 use epub_builder::{EpubBuilder, EpubContent, ReferenceType, ZipLibrary};
 use std::fs::File;
-use std::io::Cursor;
+use std::io::{self, Cursor, Write};
 use std::path::PathBuf;
 use tera::{Context as TeraContext, Tera}; // Add Tera imports
+use url::Url;
+use uuid::Uuid;
 
 // Embed the template files directly into the binary
 const TEMPLATE_HTML: &str = include_str!("template.html"); // For the main article content
@@ -27,9 +30,8 @@ fn generate_cover_xhtml(
     // Tera's `if` handles missing variables gracefully, so no need to conditionally insert `author`
     // unless we want to ensure it's an empty string vs. not present.
     // For simplicity, only insert if meaningfully present.
-    if !extracted.article_author.is_empty() && extracted.article_author != "http-epub" {
-        // Check if author is meaningful
-        context.insert("author", &extracted.article_author);
+    if let Some(author) = meaningful_author(extracted) {
+        context.insert("author", author);
     }
     context.insert("original_url", extracted.original_url.as_str());
     context.insert(
@@ -39,12 +41,13 @@ fn generate_cover_xhtml(
             .host_str()
             .unwrap_or_else(|| extracted.original_url.as_str()),
     );
-    if let Some(date) = extracted.date_published {
-        context.insert(
-            "date_published_formatted",
-            &date.format("%B %d, %Y at %l:%M %p").to_string(),
-        );
+    if let Some(date) = formatted_date_published(extracted) {
+        context.insert("date_published_formatted", &date);
+    }
+    if let Some(ref description) = extracted.description {
+        context.insert("description", description);
     }
+    context.insert("length_band", length_band(word_count(&extracted.content)));
 
     // Add epubification date (current time when the EPUB is being created)
     let epubification_date = Utc::now();
@@ -59,81 +62,323 @@ fn generate_cover_xhtml(
         .map_err(|e| anyhow!("Failed to render cover template: {}", e))
 }
 
+// Returns the article author unless it is empty or the "http-epub" fallback
+fn meaningful_author(extracted: &ExtractedContent) -> Option<&str> {
+    let author = extracted.article_author.as_str();
+    if author.is_empty() || author == "http-epub" {
+        None
+    } else {
+        Some(author)
+    }
+}
+
+fn formatted_date_published(extracted: &ExtractedContent) -> Option<String> {
+    extracted
+        .date_published
+        .map(|date| date.format("%B %d, %Y at %l:%M %p").to_string())
+}
+
+fn word_count(content_html: &str) -> usize {
+    DomDocument::from(content_html)
+        .select("body")
+        .text()
+        .split_whitespace()
+        .count()
+}
+
+/// A coarse length label for the cover, at roughly 230 words per minute:
+/// under 5 minutes is short, over 15 is long.
+fn length_band(words: usize) -> &'static str {
+    match words {
+        0..1_200 => "Short read",
+        1_200..3_500 => "Medium read",
+        _ => "Long read",
+    }
+}
+
 // Helper function to apply the article template using Tera
-fn apply_article_template(tera: &Tera, content_body: &str, title: &str) -> Result<String> {
+fn apply_article_template(
+    tera: &Tera,
+    content_body: &str,
+    title: &str,
+    author: Option<&str>,
+    date_published_formatted: Option<&str>,
+) -> Result<String> {
     let mut context = TeraContext::new();
     context.insert("title", title);
-    context.insert("content", content_body);
+    // The byline goes under the article's own title heading when the body has one;
+    // otherwise the template renders the title above it
+    let (heading, content_body) = split_at_title_heading(content_body, title);
+    context.insert("heading", &heading);
+    context.insert("content", &content_body);
+    // Only insert byline fields when present so the template can skip the block
+    if let Some(author) = author {
+        context.insert("author", author);
+    }
+    if let Some(date) = date_published_formatted {
+        context.insert("date_published_formatted", date);
+    }
 
     tera.render("template.html", &context) // Assuming "template.html" is the article template name
         .map_err(|e| anyhow!("Failed to render article template: {}", e))
 }
 
+const BYLINE_MARKER: &str = "<!--http-epub-byline-->";
+
+/// Splits the article body after its own title heading, returning `(up to and
+/// including the heading, rest)`. The title heading is a leading `<hN>` whose text is
+/// the title (as every split chapter starts), or else the body's first `<h1>`.
+/// Without one the first part is empty and the body is returned as is.
+fn split_at_title_heading(content: &str, title: &str) -> (String, String) {
+    let document = DomDocument::from(content);
+    let body_selection = document.select("body");
+    let Some(body) = body_selection.nodes().first().cloned() else {
+        return (String::new(), content.to_string());
+    };
+
+    let leading_heading = body
+        .first_element_child()
+        .filter(|first| first.is("h1, h2, h3, h4, h5, h6") && first.text().trim() == title.trim());
+    let heading_selection = body_selection.select("h1");
+    let Some(heading) = leading_heading.or_else(|| heading_selection.nodes().first().cloned())
+    else {
+        return (String::new(), content.to_string());
+    };
+
+    heading.after_html(BYLINE_MARKER);
+    let html = body.inner_html();
+    match html.split_once(BYLINE_MARKER) {
+        Some((heading, rest)) => (heading.to_string(), rest.to_string()),
+        None => (String::new(), content.to_string()),
+    }
+}
+
+/// Options controlling the structure of the generated EPUB.
+#[derive(Clone, Debug, Default)]
+pub struct EpubOptions {
+    /// Skip the generated cover page and cover image, so the article is the
+    /// first content. Title, author and date metadata are still set.
+    pub no_cover: bool,
+    /// Split the article into one chapter per heading of this level (1-6)
+    /// instead of a single `article.xhtml`.
+    pub split_by_heading: Option<u8>,
+    /// How the book's identifier is chosen.
+    pub identifier: IdentifierScheme,
+}
+
+/// Scheme for the EPUB's identifier.
+// The OPF `unique-identifier` always refers to a `urn:uuid` `dc:identifier`; the
+// schemes differ in where that UUID comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum IdentifierScheme {
+    /// A random UUID, with the article URL recorded alongside it
+    // The package id is still a random UUID; the URL is added as an extra
+    // `dc:identifier` meta.
+    #[default]
+    Url,
+    /// A random UUID, different for every build
+    Uuid,
+    /// A UUID derived from the article URL, stable when re-archiving an article
+    // A name-based (v5) UUID of the URL without its fragment, see `url_uuid`.
+    #[value(alias = "urn:uuid")]
+    UrnUuid,
+}
+
+/// A name-based (v5) UUID for the URL. The fragment only selects a position in the
+/// page, so it is dropped; the URL parser has already normalized case and ports.
+fn url_uuid(url: &Url) -> Uuid {
+    let mut normalized = url.clone();
+    normalized.set_fragment(None);
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, normalized.as_str().as_bytes())
+}
+
+/// A section of the article body that becomes its own EPUB content document
+struct Chapter {
+    title: String,
+    content: String,
+}
+
+/// Splits article HTML at each top-level `<hN>` heading. Content before the first
+/// heading becomes an introduction chapter titled after the article.
+fn split_by_heading(content: &str, level: u8, article_title: &str) -> Vec<Chapter> {
+    let document = DomDocument::from(content);
+    let heading_tag = format!("h{level}");
+    let mut chapters = vec![Chapter {
+        title: article_title.to_string(),
+        content: String::new(),
+    }];
+
+    let body_selection = document.select("body");
+    if let Some(body) = body_selection.nodes().first() {
+        for node in body.children() {
+            if node.node_name().as_deref() == Some(heading_tag.as_str()) {
+                let heading_text = node.text().trim().to_string();
+                chapters.push(Chapter {
+                    title: if heading_text.is_empty() {
+                        article_title.to_string()
+                    } else {
+                        heading_text
+                    },
+                    content: String::new(),
+                });
+            }
+            if let Some(chapter) = chapters.last_mut() {
+                chapter.content.push_str(&node.html());
+            }
+        }
+    }
+
+    // Drop the introduction if nothing but whitespace preceded the first heading
+    if chapters.len() > 1 && is_blank_html(&chapters[0].content) {
+        chapters.remove(0);
+    }
+    chapters
+}
+
+fn is_blank_html(html: &str) -> bool {
+    let document = DomDocument::from(html);
+    document.select("body").text().trim().is_empty() && document.select("img").is_empty()
+}
+
+// Most filesystems cap names at 255 bytes; leave room for ".epub" and a " (N)" collision suffix
+const MAX_FILENAME_STEM_BYTES: usize = 200;
+
+/// The file name used when no explicit output file is given, derived from the title.
+/// Falls back to the URL's domain (or a UUID) when nothing usable is left of the title.
+pub fn default_filename(extracted: &ExtractedContent) -> String {
+    let mut stem = sanitize_filename_stem(&extracted.title);
+    if stem.is_empty() {
+        stem = extracted
+            .original_url
+            .host_str()
+            .map(sanitize_filename_stem)
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        debug!(
+            title = extracted.title,
+            fallback = stem,
+            "Title is unusable as a file name; using fallback."
+        );
+    }
+    format!("{stem}.epub")
+}
+
+pub(crate) fn sanitize_filename_stem(name: &str) -> String {
+    let sanitized = sanitize_filename::sanitize_with_options(
+        name,
+        sanitize_filename::Options {
+            truncate: false,
+            ..Default::default()
+        },
+    );
+
+    let mut end = sanitized.len().min(MAX_FILENAME_STEM_BYTES);
+    while !sanitized.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Leading whitespace and trailing dots or spaces are stripped or mangled by some platforms
+    let stem = sanitized[..end].trim_start().trim_end_matches(['.', ' ']);
+
+    if is_reserved_device_name(stem) {
+        format!("{stem}_")
+    } else {
+        stem.to_string()
+    }
+}
+
+// Windows device names are unusable as file names, with or without an extension
+fn is_reserved_device_name(stem: &str) -> bool {
+    let device = stem.split('.').next().unwrap_or(stem).to_ascii_lowercase();
+    matches!(device.as_str(), "con" | "prn" | "aux" | "nul")
+        || ((device.starts_with("com") || device.starts_with("lpt"))
+            && device.len() == 4
+            && device.as_bytes()[3].is_ascii_digit())
+}
+
 pub fn create_epub(
     extracted: &ExtractedContent,
     output_path_option: Option<&PathBuf>,
 ) -> Result<PathBuf> {
-    // Renamed output_path
-    // Initialize Tera and load templates by string content
-    let mut tera = Tera::default();
-    tera.add_raw_template("template.html", TEMPLATE_HTML) // TEMPLATE_HTML is the article template
-        .context("Failed to add article template to Tera")?;
-    tera.add_raw_template("cover_template.html", COVER_TEMPLATE_HTML)
-        .context("Failed to add cover template to Tera")?;
+    create_epub_with_options(extracted, output_path_option, &EpubOptions::default())
+}
 
+pub fn create_epub_with_options(
+    extracted: &ExtractedContent,
+    output_path_option: Option<&PathBuf>,
+    options: &EpubOptions,
+) -> Result<PathBuf> {
     // Generate output path if not provided
     let mut final_path = match output_path_option {
         // Use renamed parameter
         Some(path) => {
             if path.is_dir() {
                 // If it's a directory, append the generated filename
-                let filename = sanitize_filename::sanitize(format!("{}.epub", extracted.title));
-                path.join(filename)
+                path.join(default_filename(extracted))
             } else {
                 // If it's not a directory, use the path as-is
                 path.clone()
             }
         }
-        None => {
-            let filename = sanitize_filename::sanitize(format!("{}.epub", extracted.title));
-            PathBuf::from(filename)
-        }
+        None => PathBuf::from(default_filename(extracted)),
     };
 
-    // Check if the file exists and find an alternative name if it does
-    if final_path.exists() {
-        let mut counter = 1;
-        let original_stem = final_path
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let extension = final_path
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        loop {
+    // Claim the path, or the first free alternative name, with create_new so that
+    // concurrent conversions never write to the same file
+    let file = create_unique_file(&mut final_path)?;
+    write_epub_with_options(extracted, file, options)?;
+
+    Ok(final_path)
+}
+
+fn create_unique_file(path: &mut PathBuf) -> Result<File> {
+    let original_stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let mut counter = 0;
+    loop {
+        if counter > 0 {
             let new_filename_str = if extension.is_empty() {
                 format!("{original_stem} ({counter})")
             } else {
                 format!("{original_stem} ({counter}).{extension}")
             };
-            let new_path = final_path.with_file_name(new_filename_str);
-            if !new_path.exists() {
-                final_path = new_path;
-                break;
+            *path = path.with_file_name(new_filename_str);
+        }
+        match File::create_new(&*path) {
+            Ok(file) => return Ok(file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => {
+                return Err(e).context(format!("Failed to create output file: {}", path.display()));
             }
-            counter += 1;
         }
     }
+}
 
-    // Create a new EPUB
-    let file = File::create(&final_path).context(format!(
-        "Failed to create output file: {}",
-        final_path.display()
-    ))?;
+/// Generates the EPUB into `writer`, leaving the choice of destination (file,
+/// stdout, memory buffer) to the caller
+pub fn write_epub<W: Write>(extracted: &ExtractedContent, writer: W) -> Result<()> {
+    write_epub_with_options(extracted, writer, &EpubOptions::default())
+}
+
+pub fn write_epub_with_options<W: Write>(
+    extracted: &ExtractedContent,
+    writer: W,
+    options: &EpubOptions,
+) -> Result<()> {
+    // Initialize Tera and load templates by string content
+    let mut tera = Tera::default();
+    tera.add_raw_template("template.html", TEMPLATE_HTML) // TEMPLATE_HTML is the article template
+        .context("Failed to add article template to Tera")?;
+    tera.add_raw_template("cover_template.html", COVER_TEMPLATE_HTML)
+        .context("Failed to add cover template to Tera")?;
 
     let zip_library =
         ZipLibrary::new().map_err(|e| anyhow!("Failed to create ZIP library: {}", e))?;
@@ -145,11 +390,17 @@ pub fn create_epub(
         .map_err(|e| anyhow!("Failed to set title metadata: {}", e))?;
     epub.metadata("author", &extracted.article_author)
         .map_err(|e| anyhow!("Failed to set author metadata: {}", e))?;
-    // Use add_metadata_opf for dc:identifier with the URL
-    epub.add_metadata_opf(epub_builder::MetadataOpf {
-        name: "dc:identifier".to_string(),
-        content: extracted.original_url.as_str().to_string(),
-    });
+    match options.identifier {
+        IdentifierScheme::Url => {
+            // Use add_metadata_opf for dc:identifier with the URL
+            epub.add_metadata_opf(epub_builder::MetadataOpf {
+                name: "dc:identifier".to_string(),
+                content: extracted.original_url.as_str().to_string(),
+            });
+        }
+        IdentifierScheme::Uuid => epub.set_uuid(Uuid::new_v4()),
+        IdentifierScheme::UrnUuid => epub.set_uuid(url_uuid(&extracted.original_url)),
+    }
     if let Some(date_published) = extracted.date_published {
         // Using add_metadata_opf for dc:date as per common EPUB practices
         epub.add_metadata_opf(epub_builder::MetadataOpf {
@@ -164,7 +415,9 @@ pub fn create_epub(
     // Determine cover image details from original_thumbnail_url and image_map
     let mut cover_image_local_path: Option<String> = None;
 
-    if let Some(ref original_thumb_url) = extracted.original_thumbnail_url {
+    if options.no_cover {
+        debug!("Cover page disabled; skipping cover image and cover.xhtml.");
+    } else if let Some(ref original_thumb_url) = extracted.original_thumbnail_url {
         if let Some(downloaded_cover_info) = extracted.image_map.get(original_thumb_url.as_str()) {
             debug!(
                 "Setting cover image using: {}",
@@ -191,14 +444,16 @@ pub fn create_epub(
     }
 
     // Generate and add the cover.xhtml page
-    let cover_xhtml_content =
-        generate_cover_xhtml(&tera, extracted, cover_image_local_path.as_deref())?;
-    epub.add_content(
-        EpubContent::new("cover.xhtml", cover_xhtml_content.as_bytes())
-            .title("Cover")
-            .reftype(ReferenceType::Cover),
-    )
-    .map_err(|e| anyhow!("Failed to add cover page content: {}", e))?;
+    if !options.no_cover {
+        let cover_xhtml_content =
+            generate_cover_xhtml(&tera, extracted, cover_image_local_path.as_deref())?;
+        epub.add_content(
+            EpubContent::new("cover.xhtml", cover_xhtml_content.as_bytes())
+                .title("Cover")
+                .reftype(ReferenceType::Cover),
+        )
+        .map_err(|e| anyhow!("Failed to add cover page content: {}", e))?;
+    }
 
     // Add all images from the map as resources.
     // The cover image (if set by add_cover_image) is already added as a resource by epub-builder.
@@ -230,24 +485,427 @@ pub fn create_epub(
         .map_err(|e| anyhow!("Failed to add image resource {}: {}", original_url_str, e))?;
     }
 
-    // Apply template to the body content for the article page
-    let article_xhtml_content =
-        apply_article_template(&tera, &extracted.content, &extracted.title)?;
+    let date_published_formatted = formatted_date_published(extracted);
+
+    match options.split_by_heading {
+        Some(level) => {
+            let chapters = split_by_heading(&extracted.content, level, &extracted.title);
+            debug!(
+                count = chapters.len(),
+                level, "Splitting article into chapters by heading."
+            );
+            for (index, chapter) in chapters.iter().enumerate() {
+                // Only the first chapter carries the byline
+                let is_first = index == 0;
+                let chapter_xhtml_content = apply_article_template(
+                    &tera,
+                    &chapter.content,
+                    &chapter.title,
+                    meaningful_author(extracted).filter(|_| is_first),
+                    date_published_formatted.as_deref().filter(|_| is_first),
+                )?;
+                let mut chapter_content = EpubContent::new(
+                    format!("chapter_{:03}.xhtml", index + 1),
+                    chapter_xhtml_content.as_bytes(),
+                )
+                .title(&chapter.title);
+                if is_first {
+                    chapter_content = chapter_content.reftype(ReferenceType::Text);
+                }
+                epub.add_content(chapter_content)
+                    .map_err(|e| anyhow!("Failed to add chapter '{}': {}", chapter.title, e))?;
+            }
+        }
+        None => {
+            // Apply template to the body content for the article page
+            let article_xhtml_content = apply_article_template(
+                &tera,
+                &extracted.content,
+                &extracted.title,
+                meaningful_author(extracted),
+                date_published_formatted.as_deref(),
+            )?;
 
-    // Add main content (article body)
-    epub.add_content(
-        EpubContent::new("article.xhtml", article_xhtml_content.as_bytes())
-            .title(&extracted.title)
-            .reftype(ReferenceType::Text),
-    )
-    .map_err(|e| anyhow!("Failed to add main article content: {}", e))?;
+            // Add main content (article body)
+            epub.add_content(
+                EpubContent::new("article.xhtml", article_xhtml_content.as_bytes())
+                    .title(&extracted.title)
+                    .reftype(ReferenceType::Text),
+            )
+            .map_err(|e| anyhow!("Failed to add main article content: {}", e))?;
+        }
+    }
 
     // Generate EPUB
-    epub.generate(
-        file.try_clone()
-            .context("Failed to clone file handle for EPUB generation")?,
-    ) // Ensure file is cloneable or re-opened if needed by library
-    .map_err(|e| anyhow!("Failed to generate EPUB: {}", e))?;
+    epub.generate(writer)
+        .map_err(|e| anyhow!("Failed to generate EPUB: {}", e))?;
 
-    Ok(final_path)
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::DownloadedImage;
+    use std::collections::HashMap;
+    use std::io::Read;
+
+    fn sample_extracted() -> ExtractedContent {
+        let thumbnail_url = Url::parse("https://example.com/thumb.png").unwrap();
+        let mut image_map = HashMap::new();
+        image_map.insert(
+            thumbnail_url.as_str().to_string(),
+            DownloadedImage {
+                local_path: "images/thumb.png.png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G'],
+                mime_type: "image/png",
+            },
+        );
+        ExtractedContent {
+            content: "<p>Hello, world.</p>".to_string(),
+            image_map,
+            title: "Sample Article".to_string(),
+            original_url: Url::parse("https://example.com/article").unwrap(),
+            article_author: "Jane Doe".to_string(),
+            date_published: None,
+            original_thumbnail_url: Some(thumbnail_url),
+            description: None,
+            site_name: None,
+        }
+    }
+
+    fn zip_entries(path: &PathBuf) -> zip::ZipArchive<File> {
+        zip::ZipArchive::new(File::open(path).unwrap()).unwrap()
+    }
+
+    fn article_tera() -> Tera {
+        let mut tera = Tera::default();
+        tera.add_raw_template("template.html", TEMPLATE_HTML)
+            .unwrap();
+        tera
+    }
+
+    #[test]
+    fn test_article_template_byline() {
+        let tera = article_tera();
+
+        let with_author = apply_article_template(
+            &tera,
+            "<p>Body</p>",
+            "A Title",
+            Some("Jane Doe"),
+            Some("May 31, 2025 at 10:11 AM"),
+        )
+        .unwrap();
+        assert!(with_author.contains(r#"<div class="byline">"#));
+        assert!(
+            with_author.find("<h1>A Title</h1>").unwrap()
+                < with_author.find(r#"<div class="byline">"#).unwrap()
+        );
+        assert!(with_author.contains("By Jane Doe"));
+        assert!(with_author.contains("May 31, 2025 at 10:11 AM"));
+
+        let without_author =
+            apply_article_template(&tera, "<p>Body</p>", "A Title", None, None).unwrap();
+        assert!(!without_author.contains(r#"<div class="byline">"#));
+        assert!(
+            without_author.find("<h1>A Title</h1>").unwrap()
+                < without_author.find("<p>Body</p>").unwrap()
+        );
+
+        // The byline sits under the article's own title heading
+        let with_heading = apply_article_template(
+            &tera,
+            "<html><head></head><body>\n<h1>A Title</h1><p>Body</p></body></html>",
+            "A Title",
+            Some("Jane Doe"),
+            None,
+        )
+        .unwrap();
+        let heading_at = with_heading.find("<h1>A Title</h1>").unwrap();
+        let byline_at = with_heading.find(r#"<div class="byline">"#).unwrap();
+        let body_at = with_heading.find("<p>Body</p>").unwrap();
+        assert!(heading_at < byline_at && byline_at < body_at);
+        assert_eq!(with_heading.matches("<h1>").count(), 1);
+
+        // An <h1> further down the body is still the title, and is not repeated
+        let late_heading = apply_article_template(
+            &tera,
+            r#"<div class="meta">Filed under News</div><h1>Real Title</h1><p>Body</p>"#,
+            "A Title",
+            Some("Jane Doe"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(late_heading.matches("<h1>").count(), 1);
+        let heading_at = late_heading.find("<h1>Real Title</h1>").unwrap();
+        let byline_at = late_heading.find(r#"<div class="byline">"#).unwrap();
+        let body_at = late_heading.find("<p>Body</p>").unwrap();
+        assert!(heading_at < byline_at && byline_at < body_at);
+    }
+
+    #[test]
+    fn test_split_chapters_keep_their_own_heading() {
+        let tera = article_tera();
+        let chapters = split_by_heading(
+            "<h2>First</h2><p>One</p><h2>Second</h2><p>Two</p>",
+            2,
+            "Article",
+        );
+        assert_eq!(chapters.len(), 2);
+
+        let first = apply_article_template(
+            &tera,
+            &chapters[0].content,
+            &chapters[0].title,
+            Some("Jane Doe"),
+            None,
+        )
+        .unwrap();
+        assert!(!first.contains("<h1>"));
+        assert_eq!(first.matches("First</h2>").count(), 1);
+        let heading_at = first.find("<h2>First</h2>").unwrap();
+        let byline_at = first.find(r#"<div class="byline">"#).unwrap();
+        assert!(heading_at < byline_at && byline_at < first.find("<p>One</p>").unwrap());
+
+        let second =
+            apply_article_template(&tera, &chapters[1].content, &chapters[1].title, None, None)
+                .unwrap();
+        assert!(!second.contains("<h1>"));
+        assert_eq!(second.matches("Second</h2>").count(), 1);
+    }
+
+    #[test]
+    fn test_no_cover_omits_cover_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("no-cover.epub");
+        let options = EpubOptions {
+            no_cover: true,
+            ..Default::default()
+        };
+        let path = create_epub_with_options(&sample_extracted(), Some(&output), &options).unwrap();
+
+        let mut archive = zip_entries(&path);
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        assert!(!names.iter().any(|name| name.ends_with("cover.xhtml")));
+        assert!(names.iter().any(|name| name.ends_with("article.xhtml")));
+
+        let mut opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains("<dc:title>Sample Article</dc:title>"));
+        assert!(!opf.contains(r#"type="cover""#));
+        assert!(!opf.contains(r#"name="cover""#));
+    }
+
+    fn opf_identifier(extracted: &ExtractedContent, options: &EpubOptions) -> String {
+        let mut buffer = Vec::new();
+        write_epub_with_options(extracted, &mut buffer, options).unwrap();
+        let mut opf = String::new();
+        zip::ZipArchive::new(Cursor::new(buffer))
+            .unwrap()
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains(r#"unique-identifier="epub-id-1""#));
+        let start = opf.find(r#"<dc:identifier id="epub-id-1">"#).unwrap();
+        let rest = &opf[start..];
+        rest[rest.find('>').unwrap() + 1..rest.find("</dc:identifier>").unwrap()].to_string()
+    }
+
+    #[test]
+    fn test_urn_uuid_identifier_is_stable() {
+        let options = EpubOptions {
+            identifier: IdentifierScheme::UrnUuid,
+            ..Default::default()
+        };
+        let extracted = sample_extracted();
+
+        let first = opf_identifier(&extracted, &options);
+        let second = opf_identifier(&extracted, &options);
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            format!("urn:uuid:{}", url_uuid(&extracted.original_url))
+        );
+
+        // A fragment does not change the identity of the article
+        let mut with_fragment = sample_extracted();
+        with_fragment.original_url.set_fragment(Some("comments"));
+        assert_eq!(opf_identifier(&with_fragment, &options), first);
+
+        let mut other = sample_extracted();
+        other.original_url = Url::parse("https://example.com/other").unwrap();
+        assert_ne!(opf_identifier(&other, &options), first);
+
+        // Random UUIDs differ between builds
+        let random = EpubOptions {
+            identifier: IdentifierScheme::Uuid,
+            ..Default::default()
+        };
+        assert_ne!(
+            opf_identifier(&extracted, &random),
+            opf_identifier(&extracted, &random)
+        );
+    }
+
+    #[test]
+    fn test_write_epub_into_memory() {
+        let mut buffer = Vec::new();
+        write_epub(&sample_extracted(), &mut buffer).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(buffer)).unwrap();
+        let mut mimetype = String::new();
+        archive
+            .by_name("mimetype")
+            .unwrap()
+            .read_to_string(&mut mimetype)
+            .unwrap();
+        assert_eq!(mimetype, "application/epub+zip");
+        assert!(archive.by_name("OEBPS/article.xhtml").is_ok());
+    }
+
+    #[test]
+    fn test_default_includes_cover_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("cover.epub");
+        let path = create_epub(&sample_extracted(), Some(&output)).unwrap();
+
+        let archive = zip_entries(&path);
+        assert!(
+            archive
+                .file_names()
+                .any(|name| name.ends_with("cover.xhtml"))
+        );
+    }
+
+    #[test]
+    fn test_cover_shows_description_and_length_band() {
+        let mut extracted = sample_extracted();
+        extracted.description = Some("A subtitle & more".to_string());
+        let mut tera = Tera::default();
+        tera.add_raw_template("cover_template.html", COVER_TEMPLATE_HTML)
+            .unwrap();
+
+        let cover = generate_cover_xhtml(&tera, &extracted, None).unwrap();
+        assert!(cover.contains(r#"<p class="subtitle">A subtitle &amp; more</p>"#));
+        assert!(cover.contains("Length: Short read"));
+
+        assert_eq!(length_band(1_199), "Short read");
+        assert_eq!(length_band(1_200), "Medium read");
+        assert_eq!(length_band(3_500), "Long read");
+    }
+
+    #[test]
+    fn test_split_by_heading() {
+        let content = "<h2>First</h2><p>One</p>\n<h2>Second</h2><p>Two</p><h3>Nested</h3><p>Still two</p>\n<h2>Third</h2><p>Three</p>";
+
+        let chapters = split_by_heading(content, 2, "Article");
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["First", "Second", "Third"]);
+        assert!(chapters[1].content.starts_with("<h2>Second</h2>"));
+        assert!(
+            chapters[1]
+                .content
+                .contains("<h3>Nested</h3><p>Still two</p>")
+        );
+
+        let with_intro = split_by_heading(
+            "<p>Before any heading</p><h2>First</h2><p>One</p>",
+            2,
+            "Article",
+        );
+        let titles: Vec<&str> = with_intro.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Article", "First"]);
+    }
+
+    #[test]
+    fn test_split_by_heading_creates_chapters() {
+        let mut extracted = sample_extracted();
+        extracted.content = r#"<h2>First</h2><p>One <img src="images/thumb.png.png"/></p><h2>Second</h2><p>Two</p><h2>Third</h2><p>Three</p>"#.to_string();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("split.epub");
+        let options = EpubOptions {
+            split_by_heading: Some(2),
+            ..Default::default()
+        };
+        let path = create_epub_with_options(&extracted, Some(&output), &options).unwrap();
+
+        let mut archive = zip_entries(&path);
+        let mut chapters: Vec<String> = archive
+            .file_names()
+            .filter(|name| name.contains("chapter_"))
+            .map(str::to_string)
+            .collect();
+        chapters.sort();
+        assert_eq!(
+            chapters,
+            vec![
+                "OEBPS/chapter_001.xhtml",
+                "OEBPS/chapter_002.xhtml",
+                "OEBPS/chapter_003.xhtml"
+            ]
+        );
+        assert!(
+            !archive
+                .file_names()
+                .any(|name| name.ends_with("article.xhtml"))
+        );
+
+        let mut toc = String::new();
+        archive
+            .by_name("OEBPS/toc.ncx")
+            .unwrap()
+            .read_to_string(&mut toc)
+            .unwrap();
+        assert!(toc.contains("<text>Second</text>"));
+        assert!(toc.contains("<text>Third</text>"));
+    }
+
+    fn filename_for_title(title: &str) -> String {
+        let mut extracted = sample_extracted();
+        extracted.title = title.to_string();
+        default_filename(&extracted)
+    }
+
+    #[test]
+    fn test_default_filename_truncates_overlong_title() {
+        let filename = filename_for_title(&"a".repeat(300));
+        assert_eq!(
+            filename,
+            format!("{}.epub", "a".repeat(MAX_FILENAME_STEM_BYTES))
+        );
+
+        // Multi-byte titles are cut on a character boundary
+        let filename = filename_for_title(&"é".repeat(150));
+        assert!(filename.ends_with("é.epub"));
+        assert!(filename.len() <= MAX_FILENAME_STEM_BYTES + ".epub".len());
+    }
+
+    #[test]
+    fn test_default_filename_emoji_title() {
+        assert_eq!(filename_for_title("🦀🚀"), "🦀🚀.epub");
+
+        let filename = filename_for_title(&"🦀".repeat(100));
+        assert!(filename.len() <= MAX_FILENAME_STEM_BYTES + ".epub".len());
+        assert!(
+            filename
+                .trim_end_matches(".epub")
+                .chars()
+                .all(|c| c == '🦀')
+        );
+    }
+
+    #[test]
+    fn test_default_filename_reserved_and_empty_titles() {
+        assert_eq!(filename_for_title("CON"), "CON_.epub");
+        assert_eq!(filename_for_title("lpt1"), "lpt1_.epub");
+        assert_eq!(filename_for_title("Console"), "Console.epub");
+        assert_eq!(filename_for_title("???"), "example.com.epub");
+        assert_eq!(filename_for_title(" ... "), "example.com.epub");
+    }
 }
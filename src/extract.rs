@@ -1,12 +1,17 @@
+use crate::epub;
 use crate::fetch::{DownloadedImage, FetchedContent, Fetcher};
+use crate::pdf;
 use ammonia::Builder;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use article_extractor::{Article, FullTextParser};
 use chrono::{DateTime, Utc};
 use dom_query::Document as DomDocument;
 use maplit::{hashmap, hashset};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use tracing::{debug, instrument, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 
 pub struct ExtractedContent {
@@ -17,6 +22,10 @@ pub struct ExtractedContent {
     pub article_author: String,
     pub date_published: Option<DateTime<Utc>>,
     pub original_thumbnail_url: Option<Url>,
+    /// Short summary from the page's `og:description` or `description` meta tag
+    pub description: Option<String>,
+    /// The page's `og:site_name`
+    pub site_name: Option<String>,
 }
 
 pub struct ParsedArticle {
@@ -25,9 +34,37 @@ pub struct ParsedArticle {
     pub head_document: DomDocument,
 }
 
+/// Options controlling how an [`Extractor`] post-processes article content.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Inline every downloaded image into the article as a `data:` URI instead of
+    /// referencing a separate EPUB resource. This makes the article XHTML fully
+    /// self-contained, at the cost of a noticeably larger EPUB.
+    pub flatten_images: bool,
+    /// Directory to write the fetched page HTML and the extractor's output HTML to,
+    /// for debugging extraction problems.
+    pub dump_raw_html: Option<PathBuf>,
+    /// Class, id and role words that mark cookie-consent, paywall and modal
+    /// overlays to remove before extraction. `None` uses
+    /// [`DEFAULT_OVERLAY_PATTERNS`]; an empty list disables overlay removal.
+    pub overlay_patterns: Option<Vec<String>>,
+    /// Strip a trailing ` - Site` or ` | Site` from the title when it matches the
+    /// page's `og:site_name` or domain.
+    pub strip_title_suffix: bool,
+}
+
+/// Words in class, id and role values that identify page overlays
+pub const DEFAULT_OVERLAY_PATTERNS: &[&str] = &["cookie", "consent", "paywall", "gdpr", "modal"];
+
+// Overlays are short notices; elements with more text than this are never removed
+const MAX_OVERLAY_TEXT_CHARS: usize = 600;
+// A fixed-position element stacked at least this high is treated as an overlay
+const MIN_OVERLAY_Z_INDEX: i64 = 1000;
+
 pub struct Extractor {
     fetcher: Fetcher,
     parser: FullTextParser,
+    options: ExtractOptions,
 }
 
 impl Default for Extractor {
@@ -38,15 +75,61 @@ impl Default for Extractor {
 
 impl Extractor {
     pub fn new() -> Self {
+        Self::with_options(ExtractOptions::default())
+    }
+
+    pub fn with_options(options: ExtractOptions) -> Self {
+        Self::with_fetcher(Fetcher::new(), options)
+    }
+
+    pub fn with_fetcher(fetcher: Fetcher, options: ExtractOptions) -> Self {
         Self {
-            fetcher: Fetcher::new(),
+            fetcher,
             parser: FullTextParser::new(None),
+            options,
         }
     }
+
     #[instrument(skip(self), fields(original_url))]
     pub fn process(&self, original_url: &Url) -> Result<ExtractedContent> {
         let content = self.fetcher.fetch_content(original_url)?; // `content` is FetchedContent
+        self.process_fetched(content)
+    }
+
+    /// Extracts an article from HTML the caller already has, without fetching the page.
+    /// Relative links and images are resolved against `base_url`; images are still downloaded.
+    pub fn process_html(&self, html: &str, base_url: &Url) -> Result<ExtractedContent> {
+        self.process_fetched(FetchedContent {
+            original_url: base_url.clone(),
+            url: base_url.clone(),
+            html_string: html.to_string(),
+            pdf_data: None,
+        })
+    }
+
+    /// Extracts an article from content previously returned by [`Fetcher::fetch_content`]
+    #[instrument(skip_all, fields(url = %content.url))]
+    pub fn process_fetched(&self, content: FetchedContent) -> Result<ExtractedContent> {
+        let original_url = &content.original_url;
+        if let Some(ref pdf_data) = content.pdf_data {
+            return self.process_pdf(original_url, &content.url, pdf_data);
+        }
+        // Dump the fetched page before parsing, so it is there to inspect when extraction fails
+        let dump_name = self.options.dump_raw_html.as_ref().map(|dump_dir| {
+            let name = dump_file_stem(&page_title(&content.html_string), &content.url);
+            (dump_dir, name)
+        });
+        if let Some((dump_dir, name)) = &dump_name {
+            dump_html(dump_dir, &format!("{name}.raw.html"), &content.html_string)?;
+        }
         let parsed = self.parsed_article(content.clone())?; // `parsed` is ParsedArticle
+        if let Some((dump_dir, name)) = &dump_name {
+            dump_html(
+                dump_dir,
+                &format!("{name}.extracted.html"),
+                parsed.article.html.as_deref().unwrap_or(""),
+            )?;
+        }
         let mut image_urls = self.extract_image_urls(&parsed);
 
         // Determine the absolute thumbnail URL if it exists
@@ -67,7 +150,7 @@ impl Extractor {
             image_urls.insert(abs_thumb_url.clone());
         }
 
-        let image_map = self.fetcher.download_image_list(&image_urls)?; // image_map keys are absolute URL strings
+        let mut image_map = self.fetcher.download_image_list(&image_urls)?; // image_map keys are absolute URL strings
 
         // Clean HTML first, then process DOM transformations directly
         let body_html = self.extract_body(&parsed);
@@ -76,11 +159,27 @@ impl Extractor {
         // Create a new document from cleaned HTML for further processing
         let mut cleaned_document = DomDocument::from(cleaned_body_html);
         self.convert_video_tags_to_links(&mut cleaned_document, &content.url);
+        self.uniquify_heading_ids(&mut cleaned_document);
         self.replace_image_urls(&mut cleaned_document, &image_map, &content.url);
 
+        if self.options.flatten_images {
+            // Inlined images no longer need to be packaged as resources; only the
+            // cover image is still referenced by path.
+            image_map.retain(|url_str, _| {
+                absolute_thumbnail_url
+                    .as_ref()
+                    .is_some_and(|thumb_url| thumb_url.as_str() == url_str)
+            });
+        }
+
         let final_body = cleaned_document.html().to_string();
-        let title = self.extract_title(&parsed);
+        let site_name = self.extract_site_name(&parsed);
+        let mut title = self.extract_title(&parsed);
+        if self.options.strip_title_suffix {
+            title = strip_title_suffix(&title, site_name.as_deref(), &content.url);
+        }
         let article_author = self.extract_author(&parsed);
+        let description = self.extract_description(&parsed);
         let date_published = parsed.article.date.or_else(|| {
             debug!("No date found in article_extractor, trying meta tags...");
             self._extract_date_from_meta_tags(&parsed.head_document)
@@ -100,6 +199,44 @@ impl Extractor {
             article_author,
             date_published,
             original_thumbnail_url: absolute_thumbnail_url,
+            description,
+            site_name,
+        })
+    }
+
+    #[instrument(skip_all)]
+    fn process_pdf(
+        &self,
+        original_url: &Url,
+        page_url: &Url,
+        pdf_data: &[u8],
+    ) -> Result<ExtractedContent> {
+        let pdf_text = pdf::extract_pdf(pdf_data)?;
+
+        // Fall back to the file name when the PDF carries no title metadata
+        let mut title = pdf_text.title.unwrap_or_else(|| {
+            page_url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .map(|name| name.trim_end_matches(".pdf").to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "Unknown".to_string())
+        });
+
+        if self.options.strip_title_suffix {
+            title = strip_title_suffix(&title, None, page_url);
+        }
+
+        Ok(ExtractedContent {
+            content: pdf::text_to_html(&pdf_text.text),
+            image_map: HashMap::new(),
+            title,
+            original_url: original_url.clone(),
+            article_author: pdf_text.author.unwrap_or_else(|| "http-epub".to_string()),
+            date_published: None,
+            original_thumbnail_url: None,
+            description: None,
+            site_name: None,
         })
     }
 
@@ -107,18 +244,18 @@ impl Extractor {
     fn parsed_article(&self, content: FetchedContent) -> Result<ParsedArticle> {
         // Extract just the head element content from the original HTML before moving content.html_string
         let original_document = DomDocument::from(content.html_string.as_str());
-        let head_html = if let Some(head_element) = original_document.select("head").nodes().first()
-        {
-            head_element.inner_html().to_string()
-        } else {
-            // Fallback: empty head content
-            String::new()
-        };
-        let head_document = DomDocument::from(head_html.as_str());
+        let head_html = head_inner_html(&original_document);
+
+        // Drop consent and paywall overlays so the parser cannot mistake them for the article
+        let html_for_parser = match self.options.overlay_patterns {
+            Some(ref patterns) => remove_overlays(&content.html_string, patterns),
+            None => remove_overlays(&content.html_string, DEFAULT_OVERLAY_PATTERNS),
+        }
+        .unwrap_or(content.html_string);
 
         let article_product =
             self.parser
-                .parse_offline(vec![content.html_string], None, Some(content.url))?;
+                .parse_offline(vec![html_for_parser], None, Some(content.url))?;
 
         // Get the HTML string for Document parsing.
         // If article_product.html is None, return an error.
@@ -130,19 +267,54 @@ impl Extractor {
                 "Article content (HTML) is None after parsing by article_extractor"
             ));
         }
+        let document = DomDocument::from(html_for_document_str.as_str());
+        let head_document = self.head_document(&head_html, &document, &original_document);
         Ok(ParsedArticle {
             article: article_product,
-            document: DomDocument::from(html_for_document_str.as_str()),
+            document,
             head_document,
         })
     }
 
+    /// Builds the document that author/date meta fallbacks query. When the fetched page's
+    /// `<head>` is empty, the head is re-extracted from the article HTML, and failing that
+    /// from any `<meta>` tags that ended up outside of the head.
+    fn head_document(
+        &self,
+        head_html: &str,
+        article_document: &DomDocument,
+        original_document: &DomDocument,
+    ) -> DomDocument {
+        if !head_html.trim().is_empty() {
+            return DomDocument::from(head_html);
+        }
+        warn!("Fetched page has an empty <head>; re-extracting metadata from the article HTML.");
+
+        let article_head_html = head_inner_html(article_document);
+        if !article_head_html.trim().is_empty() {
+            return DomDocument::from(article_head_html.as_str());
+        }
+
+        let stray_meta_html: String = original_document
+            .select("meta")
+            .nodes()
+            .iter()
+            .map(|meta| meta.html().to_string())
+            .collect();
+        if stray_meta_html.is_empty() {
+            warn!("No head metadata found; author and date fallbacks will be unavailable.");
+        } else {
+            debug!("Using <meta> tags found outside of <head> for metadata.");
+        }
+        DomDocument::from(stray_meta_html.as_str())
+    }
+
     fn extract_author(&self, parsed: &ParsedArticle) -> String {
-        if let Some(author) = &parsed.article.author {
-            if !author.trim().is_empty() {
-                debug!("Found author via article_extractor: {}", author);
-                return author.clone();
-            }
+        if let Some(author) = &parsed.article.author
+            && !author.trim().is_empty()
+        {
+            debug!("Found author via article_extractor: {}", author);
+            return author.clone();
         }
         debug!(
             "No author found via article_extractor or author was empty, trying meta tag fallback..."
@@ -158,13 +330,13 @@ impl Extractor {
 
         for selector in author_meta_selectors.iter() {
             let author_selection = parsed.head_document.select(selector);
-            if let Some(element) = author_selection.nodes().first() {
-                if let Some(content) = element.attr("content") {
-                    let content_str = content.to_string();
-                    if !content_str.trim().is_empty() {
-                        debug!("Found author via meta tag {}: {}", selector, content_str);
-                        return content_str;
-                    }
+            if let Some(element) = author_selection.nodes().first()
+                && let Some(content) = element.attr("content")
+            {
+                let content_str = content.to_string();
+                if !content_str.trim().is_empty() {
+                    debug!("Found author via meta tag {}: {}", selector, content_str);
+                    return content_str;
                 }
             }
         }
@@ -172,6 +344,41 @@ impl Extractor {
         "http-epub".to_string()
     }
 
+    fn extract_description(&self, parsed: &ParsedArticle) -> Option<String> {
+        let description_meta_selectors = [
+            "meta[property=\"og:description\"]",
+            "meta[name=\"description\"]",
+            "meta[name=\"twitter:description\"]",
+        ];
+
+        for selector in description_meta_selectors.iter() {
+            let description_selection = parsed.head_document.select(selector);
+            if let Some(element) = description_selection.nodes().first()
+                && let Some(content) = element.attr("content")
+            {
+                // Collapse the whitespace runs that multi-line attributes leave behind
+                let description = content.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !description.is_empty() {
+                    debug!("Found description via meta tag {}", selector);
+                    return Some(description);
+                }
+            }
+        }
+        None
+    }
+
+    fn extract_site_name(&self, parsed: &ParsedArticle) -> Option<String> {
+        let site_name_selection = parsed
+            .head_document
+            .select("meta[property=\"og:site_name\"]");
+        site_name_selection
+            .nodes()
+            .first()
+            .and_then(|element| element.attr("content"))
+            .map(|content| content.trim().to_string())
+            .filter(|content| !content.is_empty())
+    }
+
     fn _extract_date_from_meta_tags(&self, document: &DomDocument) -> Option<DateTime<Utc>> {
         let meta_selectors = [
             "meta[property=\"article:published_time\"]",
@@ -180,47 +387,43 @@ impl Extractor {
         ];
         for selector in meta_selectors.iter() {
             let date_selection = document.select(selector);
-            if let Some(element) = date_selection.nodes().first() {
-                if let Some(content) = element.attr("content") {
-                    let content_str = content.to_string();
-                    if !content_str.trim().is_empty() {
-                        debug!(tag = selector, content = content_str, "Found date meta tag");
-                        // Attempt RFC3339 first
-                        if let Ok(dt) = DateTime::parse_from_rfc3339(&content_str) {
-                            return Some(dt.with_timezone(&Utc));
+            if let Some(element) = date_selection.nodes().first()
+                && let Some(content) = element.attr("content")
+            {
+                let content_str = content.to_string();
+                if !content_str.trim().is_empty() {
+                    debug!(tag = selector, content = content_str, "Found date meta tag");
+                    // Attempt RFC3339 first
+                    if let Ok(dt) = DateTime::parse_from_rfc3339(&content_str) {
+                        return Some(dt.with_timezone(&Utc));
+                    }
+                    // Attempt custom formats
+                    let formats_to_try = [
+                        "%Y-%m-%dT%H:%M:%S%z",     // Full ISO with timezone
+                        "%Y-%m-%dT%H:%M:%S%.3f%z", // ISO with milliseconds
+                        "%Y-%m-%d %H:%M:%S %z",
+                        "%b %d, %Y %I:%M %p", // "May 31, 2025 10:11 AM"
+                        "%B %d, %Y %I:%M %p", // "May 31, 2025 10:11 AM"
+                        "%Y-%m-%d",           // Date only
+                    ];
+                    for fmt in formats_to_try.iter() {
+                        if let Ok(naive_dt) =
+                            chrono::NaiveDateTime::parse_from_str(&content_str, fmt)
+                        {
+                            debug!(format = fmt, "Successfully parsed date with custom format");
+                            return Some(DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc));
                         }
-                        // Attempt custom formats
-                        let formats_to_try = [
-                            "%Y-%m-%dT%H:%M:%S%z",     // Full ISO with timezone
-                            "%Y-%m-%dT%H:%M:%S%.3f%z", // ISO with milliseconds
-                            "%Y-%m-%d %H:%M:%S %z",
-                            "%b %d, %Y %I:%M %p", // "May 31, 2025 10:11 AM"
-                            "%B %d, %Y %I:%M %p", // "May 31, 2025 10:11 AM"
-                            "%Y-%m-%d",           // Date only
-                        ];
-                        for fmt in formats_to_try.iter() {
-                            if let Ok(naive_dt) =
-                                chrono::NaiveDateTime::parse_from_str(&content_str, fmt)
-                            {
-                                debug!(format = fmt, "Successfully parsed date with custom format");
-                                return Some(DateTime::<Utc>::from_naive_utc_and_offset(
-                                    naive_dt, Utc,
-                                ));
-                            }
-                            if let Ok(naive_date) =
-                                chrono::NaiveDate::parse_from_str(&content_str, fmt)
-                            {
-                                if let Some(dt_at_midnight) = naive_date.and_hms_opt(0, 0, 0) {
-                                    debug!(
-                                        format = fmt,
-                                        "Successfully parsed date-only with custom format"
-                                    );
-                                    return Some(DateTime::<Utc>::from_naive_utc_and_offset(
-                                        dt_at_midnight,
-                                        Utc,
-                                    ));
-                                }
-                            }
+                        if let Ok(naive_date) = chrono::NaiveDate::parse_from_str(&content_str, fmt)
+                            && let Some(dt_at_midnight) = naive_date.and_hms_opt(0, 0, 0)
+                        {
+                            debug!(
+                                format = fmt,
+                                "Successfully parsed date-only with custom format"
+                            );
+                            return Some(DateTime::<Utc>::from_naive_utc_and_offset(
+                                dt_at_midnight,
+                                Utc,
+                            ));
                         }
                     }
                 }
@@ -242,10 +445,10 @@ impl Extractor {
 
         let img_selection = parsed.document.select("img");
         for img_node in img_selection.nodes().iter() {
-            if let Some(src_str) = img_node.attr("src") {
-                if !src_str.starts_with("data:") {
-                    string_urls_to_resolve.insert(src_str.to_string());
-                }
+            if let Some(src_str) = img_node.attr("src")
+                && !src_str.starts_with("data:")
+            {
+                string_urls_to_resolve.insert(src_str.to_string());
             }
         }
 
@@ -285,11 +488,11 @@ impl Extractor {
             } else {
                 // Look for source tags inside the video
                 for child in video_element.children() {
-                    if child.node_name().as_deref() == Some("source") {
-                        if let Some(src_str) = child.attr("src") {
-                            video_url = Some(src_str.to_string());
-                            break; // Use the first source found
-                        }
+                    if child.node_name().as_deref() == Some("source")
+                        && let Some(src_str) = child.attr("src")
+                    {
+                        video_url = Some(src_str.to_string());
+                        break; // Use the first source found
                     }
                 }
             }
@@ -323,6 +526,37 @@ impl Extractor {
         }
     }
 
+    /// Renames repeated heading ids (`intro`, `intro-2`, ...) so every anchor stays unique.
+    /// The first heading with a given id keeps it, so existing links still land there.
+    #[instrument(skip_all)]
+    fn uniquify_heading_ids(&self, document: &mut DomDocument) {
+        let heading_selection = document.select("h1[id], h2[id], h3[id], h4[id], h5[id], h6[id]");
+        let headings = heading_selection.nodes();
+        let original_ids: HashSet<String> = headings
+            .iter()
+            .filter_map(|heading| heading.attr("id").map(|id| id.to_string()))
+            .collect();
+        let mut seen_ids = HashSet::new();
+
+        for heading in headings.iter() {
+            let Some(id) = heading.attr("id").map(|id| id.to_string()) else {
+                continue;
+            };
+            if seen_ids.insert(id.clone()) {
+                continue;
+            }
+            let mut counter = 2;
+            let mut unique_id = format!("{id}-{counter}");
+            while original_ids.contains(&unique_id) || seen_ids.contains(&unique_id) {
+                counter += 1;
+                unique_id = format!("{id}-{counter}");
+            }
+            debug!(id, unique_id, "Renamed duplicate heading id.");
+            heading.set_attr("id", &unique_id);
+            seen_ids.insert(unique_id);
+        }
+    }
+
     #[instrument(skip_all)]
     fn extract_body(&self, parsed: &ParsedArticle) -> String {
         let body_selection = parsed.document.select("body");
@@ -370,6 +604,7 @@ impl Extractor {
                 "figure",
                 "figcaption",
                 "table",
+                "caption",
                 "thead",
                 "tbody",
                 "tr",
@@ -384,11 +619,18 @@ impl Extractor {
                 "img" => hashset!["src", "alt", "title", "width", "height"],
                 "blockquote" => hashset!["cite"],
                 "table" => hashset!["summary"],
-                "td" => hashset!["colspan", "rowspan"],
-                "th" => hashset!["colspan", "rowspan", "scope"],
+                "td" => hashset!["colspan", "rowspan", "align", "style"],
+                "th" => hashset!["colspan", "rowspan", "scope", "align", "style"],
                 "video" => hashset!["src", "controls", "width", "height", "poster"],
-                "source" => hashset!["src", "type"]
+                "source" => hashset!["src", "type"],
+                "h1" => hashset!["id"],
+                "h2" => hashset!["id"],
+                "h3" => hashset!["id"],
+                "h4" => hashset!["id"],
+                "h5" => hashset!["id"],
+                "h6" => hashset!["id"]
             ])
+            .attribute_filter(filter_attribute)
             .url_schemes(hashset!["http", "https", "mailto"])
             .link_rel(None)
             .clean(&article_html)
@@ -424,9 +666,16 @@ impl Extractor {
                         if let Some(downloaded_image_info) =
                             image_map.get(abs_url_from_html.as_str())
                         {
-                            img_element.set_attr("src", &downloaded_image_info.local_path);
+                            if self.options.flatten_images {
+                                img_element.set_attr("src", &downloaded_image_info.to_data_uri());
+                            } else {
+                                img_element.set_attr("src", &downloaded_image_info.local_path);
+                            }
                         } else {
-                            warn!(original_src = %src_attr_val, resolved_url = %abs_url_from_html, "Image src not found in map during replacement. Keeping original src.");
+                            // Failed or filtered downloads must not leave remote
+                            // references behind, so fall back to the alt text
+                            debug!(original_src = %src_attr_val, resolved_url = %abs_url_from_html, "Image src not found in map during replacement. Replacing with alt text.");
+                            replace_missing_image(img_element);
                         }
                     }
                     Err(e) => {
@@ -438,6 +687,248 @@ impl Extractor {
     }
 }
 
+/// Replaces an image that was not downloaded with its alt text, or removes it
+/// when it has none
+fn replace_missing_image(img_element: &dom_query::NodeRef) {
+    match img_element.attr("alt").map(|alt| alt.trim().to_string()) {
+        Some(alt) if !alt.is_empty() => img_element.replace_with_html(format!(
+            r#"<span class="missing-image">[{}]</span>"#,
+            tera::escape_html(&alt)
+        )),
+        _ => img_element.remove_from_parent(),
+    }
+}
+
+/// Writes a page's HTML to `dir/file_name` for debugging, returning the written path.
+/// Used for the fetched page (`<name>.raw.html`) and the extracted article
+/// (`<name>.extracted.html`).
+pub fn dump_html(dir: &Path, file_name: &str, html: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).context(format!(
+        "Failed to create dump directory: {}",
+        dir.display()
+    ))?;
+    let path = dir.join(file_name);
+    fs::write(&path, html).context(format!("Failed to write HTML dump: {}", path.display()))?;
+    info!(path = %path.display(), "Dumped HTML.");
+    Ok(path)
+}
+
+// The page's `<title>`, read before the article is parsed
+fn page_title(html: &str) -> String {
+    DomDocument::from(html)
+        .select("title")
+        .text()
+        .trim()
+        .to_string()
+}
+
+// Names dump files after the title, or the URL when no title was found
+fn dump_file_stem(title: &str, url: &Url) -> String {
+    let from_title = epub::sanitize_filename_stem(title);
+    if !from_title.is_empty() && title != "Unknown" {
+        return from_title;
+    }
+    let url_name = format!(
+        "{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().replace('/', "_")
+    );
+    epub::sanitize_filename_stem(&url_name)
+}
+
+/// Removes a trailing ` - Site`, ` | Site` (or en/em dash) segment from a title when
+/// the segment is the site's name: it equals `og:site_name`, the host name, or one of
+/// the host's labels, ignoring case, spaces and punctuation. Any other title is
+/// returned unchanged. article_extractor already cuts some `<title>` separators on
+/// its own; this also covers the ones it misses and titles from PDF metadata.
+fn strip_title_suffix(title: &str, site_name: Option<&str>, page_url: &Url) -> String {
+    let Some((prefix, suffix)) = [" - ", " | ", " – ", " — "]
+        .iter()
+        .filter_map(|separator| title.rsplit_once(separator))
+        .max_by_key(|(prefix, _)| prefix.len())
+    else {
+        return title.to_string();
+    };
+    let prefix = prefix.trim_end();
+    let suffix_key = site_name_key(suffix);
+    if prefix.is_empty() || suffix_key.is_empty() {
+        return title.to_string();
+    }
+
+    let host = page_url.host_str().unwrap_or("");
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let labels: Vec<&str> = host.split('.').collect();
+    let mut site_keys: Vec<String> = labels[..labels.len().saturating_sub(1)]
+        .iter()
+        .filter(|label| label.len() >= 3)
+        .map(|label| site_name_key(label))
+        .collect();
+    site_keys.push(site_name_key(host));
+    site_keys.extend(site_name.map(site_name_key));
+
+    if site_keys.contains(&suffix_key) {
+        debug!(title, suffix, "Stripping site name from title.");
+        prefix.to_string()
+    } else {
+        title.to_string()
+    }
+}
+
+// Lowercase alphanumerics only, so "Example.com" and "Example Com" compare equal
+fn site_name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn head_inner_html(document: &DomDocument) -> String {
+    document
+        .select("head")
+        .nodes()
+        .first()
+        .map(|head_element| head_element.inner_html().to_string())
+        .unwrap_or_default()
+}
+
+/// Removes elements that look like cookie-consent, paywall or modal overlays.
+/// Returns `None` when nothing was removed (or `patterns` is empty), so the
+/// original HTML can be used unchanged.
+///
+/// To avoid removing real content, an element is only an overlay if it is short,
+/// contains no article, main, image, figure or table, and either has a class, id
+/// or role containing one of `patterns` as whole words, a dialog role, or a fixed
+/// position with a high `z-index`.
+fn remove_overlays<S: AsRef<str>>(html: &str, patterns: &[S]) -> Option<String> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let patterns: Vec<String> = patterns.iter().map(|p| p.as_ref().to_lowercase()).collect();
+    let document = DomDocument::from(html);
+
+    let overlays: Vec<_> = document
+        .select("body *")
+        .nodes()
+        .iter()
+        .filter(|node| is_overlay(node, &patterns))
+        .cloned()
+        .collect();
+    if overlays.is_empty() {
+        return None;
+    }
+    for overlay in &overlays {
+        debug!(
+            id = overlay.attr("id").as_deref().unwrap_or(""),
+            class = overlay.attr("class").as_deref().unwrap_or(""),
+            "Removing overlay element before extraction."
+        );
+        overlay.remove_from_parent();
+    }
+    info!(
+        count = overlays.len(),
+        "Removed page overlays before extraction."
+    );
+    Some(document.html().to_string())
+}
+
+fn is_overlay(node: &dom_query::NodeRef, patterns: &[String]) -> bool {
+    let attr_lower = |name: &str| {
+        node.attr(name)
+            .map(|v| v.to_lowercase())
+            .unwrap_or_default()
+    };
+    let (class, id, role) = (attr_lower("class"), attr_lower("id"), attr_lower("role"));
+    let matches_pattern = [&class, &id, &role]
+        .iter()
+        .flat_map(|value| value.split_whitespace())
+        .any(|token| patterns.iter().any(|pattern| has_word(token, pattern)));
+    let looks_like_overlay = matches_pattern
+        || matches!(role.as_str(), "dialog" | "alertdialog")
+        || is_fixed_on_top(&attr_lower("style"));
+
+    // The subtree checks are costlier, so only candidates get them. Media and
+    // tables are article content even when wrapped in a lightbox or modal.
+    const KEEP_SELECTOR: &str = "main, article, img, picture, figure, table";
+    looks_like_overlay
+        && !node.is(KEEP_SELECTOR)
+        && !node.descendants_it().any(|d| d.is(KEEP_SELECTOR))
+        && node.text().trim().chars().count() <= MAX_OVERLAY_TEXT_CHARS
+}
+
+/// Whether `pattern` appears in `token` as whole `-`/`_`-delimited words, so
+/// `modal` matches `modal-backdrop` and `js-modal` but not `bimodal-chart`
+fn has_word(token: &str, pattern: &str) -> bool {
+    let token_words: Vec<&str> = token.split(['-', '_']).collect();
+    let pattern_words: Vec<&str> = pattern.split(['-', '_']).collect();
+    token_words
+        .windows(pattern_words.len())
+        .any(|window| window == pattern_words.as_slice())
+}
+
+fn is_fixed_on_top(style: &str) -> bool {
+    let declarations: Vec<(&str, &str)> = style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| (property.trim(), value.trim()))
+        .collect();
+    let fixed = declarations
+        .iter()
+        .any(|&(property, value)| property == "position" && value.starts_with("fixed"));
+    let z_index = declarations
+        .iter()
+        .find(|&&(property, _)| property == "z-index")
+        .and_then(|(_, value)| {
+            value
+                .trim_end_matches("!important")
+                .trim()
+                .parse::<i64>()
+                .ok()
+        });
+    fixed && z_index.is_some_and(|z| z >= MIN_OVERLAY_Z_INDEX)
+}
+
+/// Attribute filter for the sanitizer. Table cells keep only their horizontal
+/// alignment: `align` is limited to known keywords and `style` is reduced to a
+/// single `text-align` declaration. Heading ids are kept only when they are valid.
+fn filter_attribute<'u>(element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+    match (element, attribute) {
+        ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", "id") => sanitize_id(value).map(Cow::Owned),
+        ("td" | "th", "align") => normalize_alignment(value).map(Cow::Borrowed),
+        ("td" | "th", "style") => value
+            .split(';')
+            .find_map(|declaration| {
+                let (property, alignment) = declaration.split_once(':')?;
+                if property.trim().eq_ignore_ascii_case("text-align") {
+                    normalize_alignment(alignment)
+                } else {
+                    None
+                }
+            })
+            .map(|alignment| Cow::Owned(format!("text-align: {alignment}"))),
+        _ => Some(Cow::Borrowed(value)),
+    }
+}
+
+/// Keeps a heading id as written so in-page links (`href="#..."`) still resolve.
+/// HTML5 ids may contain any character except whitespace, and the sanitizer escapes
+/// attribute values, so only empty ids and ids containing whitespace are dropped.
+fn sanitize_id(value: &str) -> Option<String> {
+    if value.is_empty() || value.chars().any(char::is_whitespace) {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+fn normalize_alignment(value: &str) -> Option<&'static str> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "left" => Some("left"),
+        "right" => Some("right"),
+        "center" => Some("center"),
+        "justify" => Some("justify"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -471,4 +962,337 @@ mod tests {
         let result3 = document3.html().to_string();
         assert!(result3.contains("Video content not available"));
     }
+
+    #[test]
+    fn test_flatten_images_uses_data_uri() {
+        let extractor = Extractor::with_options(ExtractOptions {
+            flatten_images: true,
+            ..Default::default()
+        });
+        let base_url = Url::parse("https://example.com/page").unwrap();
+        let image_map = hashmap! {
+            "https://example.com/img/photo.png".to_string() => DownloadedImage {
+                local_path: "images/photo.png.png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G'],
+                mime_type: "image/png",
+            },
+        };
+
+        let mut document = DomDocument::from(r#"<p><img src="img/photo.png" alt="Photo"></p>"#);
+        extractor.replace_image_urls(&mut document, &image_map, &base_url);
+        let result = document.html().to_string();
+        assert!(result.contains(r#"src="data:image/png;base64,iVBORw==""#));
+        assert!(!result.contains("images/photo.png.png"));
+    }
+
+    #[test]
+    fn test_missing_images_fall_back_to_alt_text() {
+        let extractor = Extractor::new();
+        let base_url = Url::parse("https://example.com/page").unwrap();
+
+        let mut document = DomDocument::from(
+            r#"<p><img src="https://tracker.example.net/chart.png" alt="Sales <2024>"><img src="https://ads.example.net/pixel.gif">Text</p>"#,
+        );
+        extractor.replace_image_urls(&mut document, &HashMap::new(), &base_url);
+        let result = document.html().to_string();
+        assert!(result.contains(r#"<span class="missing-image">[Sales &lt;2024&gt;]</span>"#));
+        assert!(!result.contains("<img"));
+        assert!(!result.contains("example.net"));
+        assert!(result.contains("Text"));
+    }
+
+    #[test]
+    fn test_clean_html_keeps_table_caption_and_alignment() {
+        let extractor = Extractor::new();
+        let html = r#"<table>
+            <caption>Quarterly revenue</caption>
+            <tr><th align="left">Quarter</th><th style="color: red; text-align: RIGHT">Revenue</th></tr>
+            <tr><td>Q1</td><td align="right" style="background: url(x)">1,200</td></tr>
+            <tr><td align="bogus" onclick="alert(1)">Q2</td><td style="color: red">900</td></tr>
+        </table>"#;
+
+        let cleaned = extractor.clean_html(html.to_string());
+
+        assert!(cleaned.contains("<caption>Quarterly revenue</caption>"));
+        assert!(cleaned.contains(r#"<th align="left">Quarter</th>"#));
+        assert!(cleaned.contains(r#"<th style="text-align: right">Revenue</th>"#));
+        assert!(cleaned.contains(r#"<td align="right">1,200</td>"#));
+        assert!(cleaned.contains("<td>Q2</td>"));
+        assert!(cleaned.contains("<td>900</td>"));
+        assert!(!cleaned.contains("color"));
+        assert!(!cleaned.contains("onclick"));
+    }
+
+    #[test]
+    fn test_metadata_from_page_without_head() {
+        let extractor = Extractor::new();
+        let url = Url::parse("https://example.com/posts/headless").unwrap();
+        let paragraph = "<p>This page forgot its head element, so its metadata was emitted inside the body instead. The extractor should still find the author and publication date.</p>";
+        let html = format!(
+            r#"<html><body><meta name="author" content="Jane Doe"><meta property="article:published_time" content="2024-03-01T10:00:00Z"><article><h1>Headless</h1>{}</article></body></html>"#,
+            paragraph.repeat(5)
+        );
+        let content = FetchedContent {
+            original_url: url.clone(),
+            url,
+            html_string: html,
+            pdf_data: None,
+        };
+
+        let parsed = extractor.parsed_article(content).unwrap();
+
+        assert_eq!(extractor.extract_author(&parsed), "Jane Doe");
+        assert_eq!(
+            extractor._extract_date_from_meta_tags(&parsed.head_document),
+            Some(
+                DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc)
+            )
+        );
+    }
+
+    #[test]
+    fn test_dump_html_writes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dump_dir = dir.path().join("dumps");
+        let url = Url::parse("https://example.com/posts/one").unwrap();
+
+        let name = dump_file_stem("A Title: With/Slashes", &url);
+        let raw_path = dump_html(
+            &dump_dir,
+            &format!("{name}.raw.html"),
+            "<html><body>raw</body></html>",
+        )
+        .unwrap();
+
+        assert_eq!(raw_path, dump_dir.join("A Title WithSlashes.raw.html"));
+        assert_eq!(
+            fs::read_to_string(&raw_path).unwrap(),
+            "<html><body>raw</body></html>"
+        );
+        assert_eq!(dump_file_stem("Unknown", &url), "example.com_posts_one");
+        assert_eq!(dump_file_stem("", &url), "example.com_posts_one");
+        assert!(dump_file_stem(&"Long title ".repeat(100), &url).len() <= 200);
+    }
+
+    #[test]
+    fn test_dump_raw_html_when_extraction_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let extractor = Extractor::with_fetcher(
+            Fetcher::new(),
+            ExtractOptions {
+                dump_raw_html: Some(dir.path().to_path_buf()),
+                ..Default::default()
+            },
+        );
+        let url = Url::parse("https://example.com/empty").unwrap();
+
+        let result = extractor.process_html("", &url);
+
+        assert!(result.is_err());
+        assert!(dir.path().join("example.com_empty.raw.html").exists());
+        assert!(!dir.path().join("example.com_empty.extracted.html").exists());
+    }
+
+    #[test]
+    fn test_process_html_without_fetching() {
+        let extractor = Extractor::new();
+        let base_url = Url::parse("https://example.com/posts/offline").unwrap();
+        let paragraph = "<p>Library users who already hold the page HTML can skip the fetch stage entirely and go straight to extraction.</p>";
+        let html = format!(
+            "<html><head><title>Offline Article</title><meta name=\"author\" content=\"Jane Doe\"></head><body><article><h1>Offline Article</h1>{}</article></body></html>",
+            paragraph.repeat(5)
+        );
+
+        let extracted = extractor.process_html(&html, &base_url).unwrap();
+
+        assert_eq!(extracted.title, "Offline Article");
+        assert_eq!(extracted.original_url, base_url);
+        assert!(extracted.content.contains("skip the fetch stage"));
+    }
+
+    #[test]
+    fn test_consent_overlay_removed_before_extraction() {
+        let extractor = Extractor::new();
+        let base_url = Url::parse("https://example.com/posts/overlay").unwrap();
+        let paragraph = "<p>The article itself is rendered underneath a consent banner that covers the whole viewport until it is dismissed.</p>";
+        let html = format!(
+            r#"<html><head><title>Behind the Banner</title></head><body><div id="cookie-consent" class="consent-banner" style="position: fixed; z-index: 9999"><p>We value your privacy. Accept cookies to continue.</p><button>Accept</button></div><article><h1>Behind the Banner</h1>{}</article></body></html>"#,
+            paragraph.repeat(5)
+        );
+
+        let cleaned = remove_overlays(&html, DEFAULT_OVERLAY_PATTERNS).unwrap();
+        assert!(!cleaned.contains("Accept cookies"));
+        assert!(cleaned.contains("underneath a consent banner"));
+
+        let extracted = extractor.process_html(&html, &base_url).unwrap();
+        assert!(!extracted.content.contains("Accept cookies"));
+        assert!(extracted.content.contains("underneath a consent banner"));
+    }
+
+    #[test]
+    fn test_overlay_removal_is_conservative() {
+        let patterns = DEFAULT_OVERLAY_PATTERNS;
+        let long_text = "A recipe section that happens to be named after cookies. ".repeat(20);
+
+        // Matching elements that hold the article or a lot of text are kept
+        let wrapper = r#"<html><body><div class="modal-open"><article><p>Story</p></article></div></body></html>"#;
+        assert_eq!(remove_overlays(wrapper, patterns), None);
+        let figure = r#"<html><body><p>Story</p><figure class="image-modal"><a class="lightbox-modal"><img src="photo.jpg"></a></figure></body></html>"#;
+        assert_eq!(remove_overlays(figure, patterns), None);
+        let chart = r#"<html><body><div class="bimodal-chart">Two peaks</div></body></html>"#;
+        assert_eq!(remove_overlays(chart, patterns), None);
+        let recipe = format!(
+            r#"<html><body><section id="cookie-recipe"><p>{long_text}</p></section></body></html>"#
+        );
+        assert_eq!(remove_overlays(&recipe, patterns), None);
+
+        // Custom patterns replace the defaults, and an empty list disables removal
+        let newsletter = r#"<html><body><div class="newsletter-popup">Subscribe</div><p>Story</p></body></html>"#;
+        assert_eq!(remove_overlays(newsletter, patterns), None);
+        let custom = remove_overlays(newsletter, &["popup"]).unwrap();
+        assert!(!custom.contains("Subscribe"));
+        assert_eq!(remove_overlays::<&str>(newsletter, &[]), None);
+
+        assert!(is_fixed_on_top(
+            "position:fixed;z-index:2147483647 !important"
+        ));
+        assert!(!is_fixed_on_top("position: fixed; z-index: 10"));
+        assert!(!is_fixed_on_top("position: absolute; z-index: 9999"));
+    }
+
+    #[test]
+    fn test_strip_title_suffix() {
+        let url = Url::parse("https://www.example.com/posts/foo").unwrap();
+
+        assert_eq!(
+            strip_title_suffix("How to Foo - Example.com", None, &url),
+            "How to Foo"
+        );
+        assert_eq!(
+            strip_title_suffix("How to Foo | Example Blog", Some("Example Blog"), &url),
+            "How to Foo"
+        );
+        assert_eq!(
+            strip_title_suffix("How to Foo – example", None, &url),
+            "How to Foo"
+        );
+
+        // Dashes that are part of the title are left alone
+        for title in [
+            "Spider-Man - A Retrospective",
+            "Rust 2024 - What Changed | Part 2",
+            "- Example",
+        ] {
+            assert_eq!(strip_title_suffix(title, Some("Example Blog"), &url), title);
+        }
+    }
+
+    #[test]
+    fn test_title_suffix_stripped_when_enabled() {
+        let base_url = Url::parse("https://news.example.org/posts/foo").unwrap();
+        let paragraph = "<p>Titles often carry the site name after a separator, which clutters file names and library listings.</p>";
+        let html = format!(
+            r#"<html><head><title>How to Foo – The Daily Example</title><meta property="og:site_name" content="The Daily Example"></head><body><article>{}</article></body></html>"#,
+            paragraph.repeat(5)
+        );
+
+        let kept = Extractor::new().process_html(&html, &base_url).unwrap();
+        assert_eq!(kept.title, "How to Foo – The Daily Example");
+        assert_eq!(kept.site_name.as_deref(), Some("The Daily Example"));
+
+        let extractor = Extractor::with_options(ExtractOptions {
+            strip_title_suffix: true,
+            ..Default::default()
+        });
+        let stripped = extractor.process_html(&html, &base_url).unwrap();
+        assert_eq!(stripped.title, "How to Foo");
+    }
+
+    #[test]
+    fn test_description_from_og_description() {
+        let extractor = Extractor::new();
+        let base_url = Url::parse("https://example.com/posts/described").unwrap();
+        let paragraph = "<p>Covers can show a subtitle taken from the page's social sharing metadata when one is present.</p>";
+        let html = format!(
+            r#"<html><head><title>Described</title><meta name="description" content="Generic site description"><meta property="og:description" content="A short   summary
+            of the article"></head><body><article><h1>Described</h1>{}</article></body></html>"#,
+            paragraph.repeat(5)
+        );
+
+        let extracted = extractor.process_html(&html, &base_url).unwrap();
+
+        assert_eq!(
+            extracted.description.as_deref(),
+            Some("A short summary of the article")
+        );
+    }
+
+    #[test]
+    fn test_clean_html_keeps_valid_heading_ids() {
+        let extractor = Extractor::new();
+        let html = r#"<h2 id="getting-started">Getting started</h2>
+            <h3 id="1.-Install-&quot;it&quot;">Install</h3>
+            <h3 id="&lt;script&gt;alert(1)&lt;/script&gt;">Run</h3>
+            <h4 id="has space">Spaces are not allowed</h4>
+            <h4 id="">Empty</h4>
+            <p id="para">Paragraph ids are still dropped</p>"#;
+
+        let cleaned = extractor.clean_html(html.to_string());
+
+        assert!(cleaned.contains(r#"<h2 id="getting-started">Getting started</h2>"#));
+        assert!(cleaned.contains(r#"<h3 id="1.-Install-&quot;it&quot;">Install</h3>"#));
+        let document = DomDocument::from(cleaned.as_str());
+        assert!(
+            document
+                .select(r#"h3[id="<script>alert(1)</script>"]"#)
+                .exists()
+        );
+        assert!(!document.select("script").exists());
+        assert!(cleaned.contains("<h4>Spaces are not allowed</h4>"));
+        assert!(cleaned.contains("<h4>Empty</h4>"));
+        assert!(cleaned.contains("<p>Paragraph ids are still dropped</p>"));
+    }
+
+    #[test]
+    fn test_clean_html_keeps_toc_links_resolving() {
+        let extractor = Extractor::new();
+        let html = r##"<ul><li><a href="#1.-Install">Install</a></li><li><a href="#étape_2">Next</a></li></ul>
+            <h2 id="1.-Install">Install</h2>
+            <h2 id="étape_2">Next</h2>"##;
+
+        let cleaned = extractor.clean_html(html.to_string());
+        let document = DomDocument::from(cleaned.as_str());
+
+        for link in document.select("a[href]").nodes() {
+            let href = link.attr("href").unwrap();
+            let target = href.strip_prefix('#').unwrap();
+            let matching = document
+                .select("h2[id]")
+                .nodes()
+                .iter()
+                .filter(|heading| heading.attr("id").as_deref() == Some(target))
+                .count();
+            assert_eq!(matching, 1, "link {href} does not resolve in {cleaned}");
+        }
+    }
+
+    #[test]
+    fn test_uniquify_heading_ids() {
+        let extractor = Extractor::new();
+        let mut document = DomDocument::from(
+            r#"<h2 id="notes">Notes</h2><h3 id="notes">More notes</h3><h2 id="notes-2">Notes 2</h2><h2 id="notes">Even more</h2>"#,
+        );
+
+        extractor.uniquify_heading_ids(&mut document);
+
+        let ids: Vec<String> = document
+            .select("h2, h3")
+            .nodes()
+            .iter()
+            .filter_map(|heading| heading.attr("id").map(|id| id.to_string()))
+            .collect();
+        assert_eq!(ids, vec!["notes", "notes-3", "notes-2", "notes-4"]);
+    }
 }
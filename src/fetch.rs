@@ -1,10 +1,26 @@
 use anyhow::{Context, Result, anyhow};
-use reqwest::blocking::Client;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use dom_query::Document as DomDocument;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{LOCATION, RETRY_AFTER};
+use reqwest::redirect::Policy;
 use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::thread;
+use std::time::Duration;
 use tracing::{debug, info, warn};
 use url::Url;
 use uuid::Uuid;
 
+// Wait used for a 429 response that does not say how long to back off
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// Meta refreshes with a longer delay are page auto-reloads rather than redirects
+const MAX_META_REFRESH_DELAY_SECS: f64 = 5.0;
+
 #[derive(Clone, Debug)]
 pub struct DownloadedImage {
     pub local_path: String,
@@ -12,15 +28,61 @@ pub struct DownloadedImage {
     pub mime_type: &'static str,
 }
 
+impl DownloadedImage {
+    /// Encodes the image as a `data:` URI suitable for an inline `src` attribute.
+    pub fn to_data_uri(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            BASE64.encode(&self.data)
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FetchedContent {
     pub original_url: Url,
     pub url: Url,
     pub html_string: String,
+    /// Raw document bytes when the response was a PDF rather than HTML.
+    /// `html_string` is empty in that case.
+    pub pdf_data: Option<Vec<u8>>,
+}
+
+/// Options controlling how a [`Fetcher`] talks to remote servers.
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// How many times a throttled request (429, or 503 with `Retry-After`) is retried
+    pub max_retries: u32,
+    /// Upper bound on how long a single `Retry-After` wait may last
+    pub max_retry_wait: Duration,
+    /// Maximum number of HTTP redirects to follow; 0 disables redirects
+    pub max_redirects: usize,
+    /// Maximum number of `<meta http-equiv="refresh">` redirects to follow; 0 disables them
+    pub max_meta_refreshes: usize,
+    /// When non-empty, only images from these hosts (or their subdomains) are downloaded
+    pub image_allow_hosts: Vec<String>,
+    /// Images from these hosts (or their subdomains) are never downloaded; this
+    /// takes precedence over `image_allow_hosts`
+    pub image_deny_hosts: Vec<String>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            max_retry_wait: Duration::from_secs(60),
+            max_redirects: 10,
+            max_meta_refreshes: 5,
+            image_allow_hosts: Vec::new(),
+            image_deny_hosts: Vec::new(),
+        }
+    }
 }
 
 pub struct Fetcher {
     client: Client,
+    options: FetchOptions,
 }
 
 impl Default for Fetcher {
@@ -31,32 +93,158 @@ impl Default for Fetcher {
 
 impl Fetcher {
     pub fn new() -> Self {
-        Self {
-            client: Client::new(),
+        Self::with_options(FetchOptions::default())
+    }
+
+    pub fn with_options(options: FetchOptions) -> Self {
+        let client = Client::builder()
+            .redirect(redirect_policy(options.max_redirects))
+            .build()
+            // Same failure mode as Client::new(): only if the TLS backend cannot initialize
+            .expect("Failed to build HTTP client");
+        Self { client, options }
+    }
+
+    /// Sends a GET request, waiting out `429 Too Many Requests` and `503 Service Unavailable`
+    /// responses as directed by their `Retry-After` header before trying again.
+    fn get_with_retry(&self, url: &Url) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let response = self.client.get(url.clone()).send()?;
+            if attempt >= self.options.max_retries {
+                return Ok(response);
+            }
+            let Some(delay) = self.retry_delay(&response) else {
+                return Ok(response);
+            };
+
+            attempt += 1;
+            warn!(
+                url = %url,
+                status = %response.status(),
+                delay_secs = delay.as_secs_f64(),
+                attempt,
+                "Server is throttling requests; waiting before retrying."
+            );
+            thread::sleep(delay);
         }
     }
 
+    /// Returns how long to wait before retrying, or None if the response should not be retried
+    fn retry_delay(&self, response: &Response) -> Option<Duration> {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, Utc::now()));
+
+        let delay = match response.status() {
+            StatusCode::TOO_MANY_REQUESTS => retry_after.unwrap_or(DEFAULT_RETRY_DELAY),
+            StatusCode::SERVICE_UNAVAILABLE => retry_after?,
+            _ => return None,
+        };
+        Some(delay.min(self.options.max_retry_wait))
+    }
+
     pub fn fetch_content(&self, url: &Url) -> Result<FetchedContent> {
         let pf_url = self.get_print_friendly_url(url);
+        let mut content = self.fetch_page(url, &pf_url)?;
 
+        // Follow <meta http-equiv="refresh"> redirects used by interstitial and consent pages
+        let mut visited = HashSet::from([pf_url, content.url.clone()]);
+        let mut hops = 0;
+        while let Some(target) = meta_refresh_target(&content) {
+            if visited.contains(&target) {
+                warn!(url = %target, "Meta refresh loop detected; keeping the current page.");
+                break;
+            }
+            if hops >= self.options.max_meta_refreshes {
+                warn!(
+                    url = %target,
+                    limit = self.options.max_meta_refreshes,
+                    "Meta refresh limit reached; keeping the current page."
+                );
+                break;
+            }
+            hops += 1;
+            info!(from = %content.url, to = %target, "Following meta refresh.");
+            visited.insert(target.clone());
+            content = self.fetch_page(url, &target)?;
+            visited.insert(content.url.clone());
+        }
+
+        Ok(content)
+    }
+
+    fn fetch_page(&self, url: &Url, pf_url: &Url) -> Result<FetchedContent> {
         // Fetch the website content
         info!(url = %pf_url, "Fetching main HTML content...");
         let response = self
-            .client
-            .get(pf_url.clone())
-            .send()
+            .get_with_retry(pf_url)
             .context("Failed to fetch website content")?;
 
-        let html = response
-            .text()
-            .context("Failed to extract text from response")?;
+        // With redirects disabled the 3xx response itself comes back; its body is not the page
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("<unknown>");
+            return Err(anyhow!(
+                "{} redirected to {} ({}), but redirects are disabled",
+                pf_url,
+                location,
+                response.status()
+            ));
+        }
+
+        // An error page (or a throttling response that outlasted the retries) is not the article
+        let status = response.status();
+        if !status.is_success() {
+            if self.retry_delay(&response).is_some() {
+                return Err(anyhow!(
+                    "Failed to fetch {}: HTTP {} after {} retries",
+                    pf_url,
+                    status.as_u16(),
+                    self.options.max_retries
+                ));
+            }
+            return Err(anyhow!("Failed to fetch {}: HTTP {}", pf_url, status));
+        }
+
+        // The effective URL after redirects, used as the base for relative links
+        let final_url = response.url().clone();
+        if final_url != *pf_url {
+            debug!(from = %pf_url, to = %final_url, "Followed redirects.");
+        }
+
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let body = response.bytes().context("Failed to read response body")?;
+
+        if is_pdf(content_type.as_deref(), &body) {
+            debug!(pdf_len = body.len(), "Main content is a PDF document.");
+            return Ok(FetchedContent {
+                original_url: url.clone(),
+                url: final_url,
+                html_string: String::new(),
+                pdf_data: Some(body.to_vec()),
+            });
+        }
+
+        let html = String::from_utf8_lossy(&body).into_owned();
 
         debug!(html_len = html.len(), "Main HTML content fetched.");
 
         Ok(FetchedContent {
             original_url: url.clone(),
-            url: pf_url,
+            url: final_url,
             html_string: html,
+            pdf_data: None,
         })
     }
 
@@ -116,13 +304,25 @@ impl Fetcher {
             "Starting to download identified images..."
         );
 
+        let mut used_paths = HashSet::new();
+
         for url in image_urls {
+            if !self.image_host_allowed(url) {
+                info!(url = %url, "Skipping image from a filtered host.");
+                continue;
+            }
             debug!(url = %url, "Attempting to download image.");
             match self.download_image(url) {
                 Ok((image_binary_data, image_mime_type)) => {
                     let base_name = self.generate_unique_filename(url);
                     let extension = self.mime_type_to_extension(image_mime_type);
-                    let local_img_path = format!("images/{base_name}.{extension}");
+                    let mut local_img_path = format!("images/{base_name}.{extension}");
+                    // Guard against the (unlikely) case of two URLs hashing alike
+                    let mut counter = 2;
+                    while !used_paths.insert(local_img_path.clone()) {
+                        local_img_path = format!("images/{base_name}-{counter}.{extension}");
+                        counter += 1;
+                    }
 
                     let downloaded_image_info = DownloadedImage {
                         local_path: local_img_path.clone(),
@@ -149,9 +349,22 @@ impl Fetcher {
         Ok(image_map)
     }
 
+    /// Applies the image host allow and deny lists; deny wins when both match
+    fn image_host_allowed(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let matches_any =
+            |patterns: &[String]| patterns.iter().any(|pattern| host_matches(&host, pattern));
+
+        if matches_any(&self.options.image_deny_hosts) {
+            return false;
+        }
+        self.options.image_allow_hosts.is_empty() || matches_any(&self.options.image_allow_hosts)
+    }
+
     pub fn generate_unique_filename(&self, url: &Url) -> String {
         // Extract the filename from the URL or generate a unique ID
-        url.path_segments()
+        let name = url
+            .path_segments()
             .and_then(|mut segments| segments.next_back())
             .and_then(|name| {
                 if name.is_empty() {
@@ -160,7 +373,13 @@ impl Fetcher {
                     Some(name.to_string())
                 }
             })
-            .unwrap_or_else(|| Uuid::new_v4().to_string())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        // Prefix with a short hash of the full URL so identically named images from
+        // different directories (or with different queries) don't overwrite each other
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        format!("{:08x}-{name}", hasher.finish() as u32)
     }
 
     pub fn mime_type_to_extension(&self, mime_type: &str) -> &str {
@@ -177,9 +396,7 @@ impl Fetcher {
     pub fn download_image(&self, img_url: &Url) -> Result<(Vec<u8>, &'static str)> {
         // Fetch the image
         let response = self
-            .client
-            .get(img_url.clone())
-            .send()
+            .get_with_retry(img_url)
             .context(format!("Failed to fetch image from {img_url}"))?;
 
         // Check if the request was successful
@@ -215,3 +432,440 @@ impl Fetcher {
         Ok((data.to_vec(), mime_type))
     }
 }
+
+/// Detects a PDF response by its Content-Type or, failing that, the `%PDF-` magic bytes
+fn is_pdf(content_type: Option<&str>, body: &[u8]) -> bool {
+    content_type.is_some_and(|t| t.contains("application/pdf")) || body.starts_with(b"%PDF-")
+}
+
+/// Returns the target of a short-delay `<meta http-equiv="refresh">` in an HTML page,
+/// resolved against the page URL
+fn meta_refresh_target(content: &FetchedContent) -> Option<Url> {
+    if content.pdf_data.is_some() {
+        return None;
+    }
+    let document = DomDocument::from(content.html_string.as_str());
+    let meta_selection = document.select("meta[http-equiv][content]");
+    let refresh = meta_selection.nodes().iter().find(|meta| {
+        meta.attr("http-equiv")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"))
+    })?;
+    let directive = refresh.attr("content")?.to_string();
+
+    // Format: "<delay>[;|,] url=<target>", with the "url=" prefix and quotes optional
+    let (delay, target) = directive.split_once([';', ','])?;
+    let delay: f64 = delay.trim().parse().ok()?;
+    if delay > MAX_META_REFRESH_DELAY_SECS {
+        debug!(delay, "Ignoring meta refresh with a long delay.");
+        return None;
+    }
+    let target = target.trim();
+    let target = match target.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url=") => &target[4..],
+        _ => target,
+    };
+    let target = target.trim().trim_matches(['\'', '"']).trim();
+    if target.is_empty() {
+        return None;
+    }
+
+    match content.url.join(target) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            warn!(target, error = %e, "Failed to resolve meta refresh URL");
+            None
+        }
+    }
+}
+
+/// Whether `host` is `pattern` or one of its subdomains, so `example.com` matches
+/// `cdn.example.com` but not `badexample.com`
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().trim_start_matches('.').to_ascii_lowercase();
+    !pattern.is_empty()
+        && host
+            .strip_suffix(pattern.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// Follows up to `max_redirects` redirects, failing early when a URL repeats
+fn redirect_policy(max_redirects: usize) -> Policy {
+    if max_redirects == 0 {
+        return Policy::none();
+    }
+    Policy::custom(move |attempt| {
+        if attempt.previous().contains(attempt.url()) {
+            let error = format!("Redirect loop detected at {}", attempt.url());
+            attempt.error(error)
+        } else if attempt.previous().len() > max_redirects {
+            let error = format!("Too many redirects (limit {max_redirects})");
+            attempt.error(error)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Parses a `Retry-After` value, either delay-seconds or an HTTP-date.
+/// Dates in the past yield a zero delay.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_fetch_retries_after_429() {
+        let mut server = mockito::Server::new();
+        let throttled = server
+            .mock("GET", "/article")
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .expect(1)
+            .create();
+        let ok = server
+            .mock("GET", "/article")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><p>Finally</p></body></html>")
+            .expect(1)
+            .create();
+
+        let url = Url::parse(&format!("{}/article", server.url())).unwrap();
+        let content = Fetcher::new().fetch_content(&url).unwrap();
+
+        throttled.assert();
+        ok.assert();
+        assert!(content.html_string.contains("Finally"));
+    }
+
+    #[test]
+    fn test_fetch_fails_when_still_throttled() {
+        let mut server = mockito::Server::new();
+        let throttled = server
+            .mock("GET", "/article")
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .with_body("<html><body><p>Slow down</p></body></html>")
+            .expect(3)
+            .create();
+
+        let url = Url::parse(&format!("{}/article", server.url())).unwrap();
+        let fetcher = Fetcher::with_options(FetchOptions {
+            max_retries: 2,
+            ..Default::default()
+        });
+        let error = fetcher.fetch_content(&url).unwrap_err();
+
+        throttled.assert();
+        assert!(
+            error.to_string().contains("HTTP 429 after 2 retries"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_download_image_retries_after_503() {
+        let mut server = mockito::Server::new();
+        let unavailable = server
+            .mock("GET", "/image.png")
+            .with_status(503)
+            .with_header("Retry-After", "0")
+            .expect(1)
+            .create();
+        let ok = server
+            .mock("GET", "/image.png")
+            .with_status(200)
+            .with_header("content-type", "image/png")
+            .with_body([0x89, b'P', b'N', b'G'])
+            .expect(1)
+            .create();
+
+        let url = Url::parse(&format!("{}/image.png", server.url())).unwrap();
+        let (data, mime_type) = Fetcher::new().download_image(&url).unwrap();
+
+        unavailable.assert();
+        ok.assert();
+        assert_eq!(mime_type, "image/png");
+        assert_eq!(data, vec![0x89, b'P', b'N', b'G']);
+    }
+
+    // Serves /hop0 -> /hop1 -> ... -> /hop{hops}, which returns the page
+    fn redirect_chain(server: &mut mockito::Server, hops: usize) {
+        for hop in 0..hops {
+            server
+                .mock("GET", format!("/hop{hop}").as_str())
+                .with_status(302)
+                .with_header("Location", &format!("/hop{}", hop + 1))
+                .with_body("redirecting")
+                .create();
+        }
+        server
+            .mock("GET", format!("/hop{hops}").as_str())
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><p>Destination</p></body></html>")
+            .create();
+    }
+
+    fn fetcher_with_max_redirects(max_redirects: usize) -> Fetcher {
+        Fetcher::with_options(FetchOptions {
+            max_redirects,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_redirects_within_limit_report_final_url() {
+        let mut server = mockito::Server::new();
+        redirect_chain(&mut server, 2);
+
+        let url = Url::parse(&format!("{}/hop0", server.url())).unwrap();
+        let content = fetcher_with_max_redirects(2).fetch_content(&url).unwrap();
+
+        assert!(content.html_string.contains("Destination"));
+        assert_eq!(content.url.path(), "/hop2");
+        assert_eq!(content.original_url, url);
+    }
+
+    #[test]
+    fn test_redirect_limit_exceeded() {
+        let mut server = mockito::Server::new();
+        redirect_chain(&mut server, 3);
+
+        let url = Url::parse(&format!("{}/hop0", server.url())).unwrap();
+        let error = fetcher_with_max_redirects(2)
+            .fetch_content(&url)
+            .unwrap_err();
+
+        assert!(format!("{error:#}").contains("Too many redirects (limit 2)"));
+    }
+
+    #[test]
+    fn test_redirects_disabled() {
+        let mut server = mockito::Server::new();
+        redirect_chain(&mut server, 1);
+
+        let url = Url::parse(&format!("{}/hop0", server.url())).unwrap();
+        let error = fetcher_with_max_redirects(0)
+            .fetch_content(&url)
+            .unwrap_err();
+
+        let message = format!("{error:#}");
+        assert!(message.contains("redirected to /hop1"));
+        assert!(message.contains("redirects are disabled"));
+    }
+
+    #[test]
+    fn test_redirect_loop_detected() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/ping")
+            .with_status(302)
+            .with_header("Location", "/pong")
+            .create();
+        server
+            .mock("GET", "/pong")
+            .with_status(302)
+            .with_header("Location", "/ping")
+            .create();
+
+        let url = Url::parse(&format!("{}/ping", server.url())).unwrap();
+        let error = Fetcher::new().fetch_content(&url).unwrap_err();
+
+        assert!(format!("{error:#}").contains("Redirect loop detected"));
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("cdn.example.com", "example.com"));
+        assert!(host_matches("cdn.example.com", ".Example.com"));
+        assert!(!host_matches("badexample.com", "example.com"));
+        assert!(!host_matches("example.com", "cdn.example.com"));
+        assert!(!host_matches("example.com", ""));
+    }
+
+    #[test]
+    fn test_denied_image_host_is_not_downloaded() {
+        let mut server = mockito::Server::new();
+        let image = server
+            .mock("GET", "/pixel.png")
+            .with_header("content-type", "image/png")
+            .with_body([0x89, b'P', b'N', b'G'])
+            .expect(1)
+            .create();
+        // The same server is reachable under two host names
+        let port = server
+            .host_with_port()
+            .rsplit(':')
+            .next()
+            .unwrap()
+            .to_string();
+        let allowed = Url::parse(&format!("http://127.0.0.1:{port}/pixel.png")).unwrap();
+        let denied = Url::parse(&format!("http://localhost:{port}/pixel.png")).unwrap();
+
+        let fetcher = Fetcher::with_options(FetchOptions {
+            image_allow_hosts: vec!["127.0.0.1".to_string(), "localhost".to_string()],
+            image_deny_hosts: vec!["localhost".to_string()],
+            ..Default::default()
+        });
+        let image_map = fetcher
+            .download_image_list(&HashSet::from([allowed.clone(), denied.clone()]))
+            .unwrap();
+
+        image.assert();
+        assert!(image_map.contains_key(allowed.as_str()));
+        assert!(!image_map.contains_key(denied.as_str()));
+
+        let allow_only = Fetcher::with_options(FetchOptions {
+            image_allow_hosts: vec!["example.com".to_string()],
+            ..Default::default()
+        });
+        assert!(!allow_only.image_host_allowed(&allowed));
+    }
+
+    #[test]
+    fn test_same_named_images_get_distinct_paths() {
+        let mut server = mockito::Server::new();
+        for path in ["/a/image.jpg", "/b/image.jpg"] {
+            server
+                .mock("GET", path)
+                .with_header("content-type", "image/jpeg")
+                .with_body(path)
+                .create();
+        }
+        let first = Url::parse(&format!("{}/a/image.jpg", server.url())).unwrap();
+        let second = Url::parse(&format!("{}/b/image.jpg", server.url())).unwrap();
+
+        let fetcher = Fetcher::new();
+        assert_eq!(
+            fetcher.generate_unique_filename(&first),
+            fetcher.generate_unique_filename(&first)
+        );
+        assert!(
+            fetcher
+                .generate_unique_filename(&first)
+                .ends_with("-image.jpg")
+        );
+
+        let image_map = fetcher
+            .download_image_list(&HashSet::from([first.clone(), second.clone()]))
+            .unwrap();
+        let first_image = &image_map[first.as_str()];
+        let second_image = &image_map[second.as_str()];
+        assert_ne!(first_image.local_path, second_image.local_path);
+        assert_eq!(first_image.data, b"/a/image.jpg");
+        assert_eq!(second_image.data, b"/b/image.jpg");
+    }
+
+    #[test]
+    fn test_meta_refresh_target_parsing() {
+        let page = |html: &str| FetchedContent {
+            original_url: Url::parse("https://example.com/gate").unwrap(),
+            url: Url::parse("https://example.com/gate").unwrap(),
+            html_string: html.to_string(),
+            pdf_data: None,
+        };
+
+        let target = meta_refresh_target(&page(
+            r#"<meta http-equiv="Refresh" content="0; URL='/real/article'">"#,
+        ));
+        assert_eq!(
+            target.map(String::from).as_deref(),
+            Some("https://example.com/real/article")
+        );
+        let target = meta_refresh_target(&page(
+            r#"<meta http-equiv="refresh" content="1;https://other.example/post">"#,
+        ));
+        assert_eq!(
+            target.map(String::from).as_deref(),
+            Some("https://other.example/post")
+        );
+        // Long delays are auto-reloads, and a bare delay reloads the same page
+        assert!(
+            meta_refresh_target(&page(
+                r#"<meta http-equiv="refresh" content="300; url=/next">"#
+            ))
+            .is_none()
+        );
+        assert!(meta_refresh_target(&page(r#"<meta http-equiv="refresh" content="0">"#)).is_none());
+    }
+
+    #[test]
+    fn test_fetch_follows_meta_refresh() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/consent")
+            .with_header("content-type", "text/html")
+            .with_body(
+                r#"<html><head><meta http-equiv="refresh" content="0;url=/article"></head><body>Accept cookies to continue.</body></html>"#,
+            )
+            .create();
+        server
+            .mock("GET", "/article")
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><p>The real article</p></body></html>")
+            .create();
+
+        let url = Url::parse(&format!("{}/consent", server.url())).unwrap();
+        let content = Fetcher::new().fetch_content(&url).unwrap();
+
+        assert!(content.html_string.contains("The real article"));
+        assert_eq!(content.url.path(), "/article");
+        assert_eq!(content.original_url, url);
+    }
+
+    #[test]
+    fn test_fetch_stops_on_meta_refresh_loop() {
+        let mut server = mockito::Server::new();
+        let first = server
+            .mock("GET", "/first")
+            .with_body(r#"<meta http-equiv="refresh" content="0;url=/second">"#)
+            .expect(1)
+            .create();
+        let second = server
+            .mock("GET", "/second")
+            .with_body(r#"<meta http-equiv="refresh" content="0;url=/first">"#)
+            .expect(1)
+            .create();
+
+        let url = Url::parse(&format!("{}/first", server.url())).unwrap();
+        let content = Fetcher::new().fetch_content(&url).unwrap();
+
+        first.assert();
+        second.assert();
+        assert_eq!(content.url.path(), "/second");
+    }
+}
@@ -1,30 +1,128 @@
-use crate::extract::Extractor;
+use crate::epub::EpubOptions;
+use crate::extract::{ExtractOptions, Extractor};
+use crate::fetch::{FetchOptions, Fetcher};
 use anyhow::Result;
 use std::path::PathBuf;
+use tracing::warn;
 use url::Url;
 
 // Re-export modules
+pub mod batch;
 pub mod cli;
 pub mod epub;
 pub mod extract;
 pub mod fetch;
+pub mod pdf;
+pub mod server;
+
+pub use crate::extract::ExtractedContent;
+
+/// Options for a single URL-to-EPUB conversion
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub fetch: FetchOptions,
+    pub extract: ExtractOptions,
+    pub epub: EpubOptions,
+}
+
+impl Options {
+    /// Builds an extractor (and its fetcher) configured from these options
+    pub fn extractor(&self) -> Extractor {
+        if self.extract.flatten_images {
+            warn!(
+                "Flattening images into data URIs; this inflates EPUB output and is intended for single-file HTML use."
+            );
+        }
+        let fetcher = Fetcher::with_options(self.fetch.clone());
+        Extractor::with_fetcher(fetcher, self.extract.clone())
+    }
+}
+
+/// An EPUB generated in memory, along with the file name it would be saved under
+pub struct EpubBytes {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
 
 /// Convert a URL to EPUB format and save to a file
 pub fn url_to_epub(url_str: &str, output_path: Option<&PathBuf>) -> Result<PathBuf> {
+    url_to_epub_with_options(url_str, output_path, &Options::default())
+}
+
+/// Convert a URL to EPUB format using the given options and save to a file
+pub fn url_to_epub_with_options(
+    url_str: &str,
+    output_path: Option<&PathBuf>,
+    options: &Options,
+) -> Result<PathBuf> {
     // Returns the path where the EPUB was saved
     // This function handles the entire process and saves to a file
 
-    let url = Url::parse(url_str)
-        .map_err(|e| anyhow::anyhow!("Failed to parse input URL '{}': {}", url_str, e))?;
+    let url = parse_input_url(url_str)?;
 
-    let extractor = Extractor::new();
+    let extractor = options.extractor();
 
     // Process document - extract content, handle images, etc.
     let extracted_content = extractor.process(&url)?;
 
     // Create EPUB and save to file
-    let final_output_path = epub::create_epub(&extracted_content, output_path)?;
+    let final_output_path =
+        epub::create_epub_with_options(&extracted_content, output_path, &options.epub)?;
 
     // Return the path where the EPUB was saved
     Ok(final_output_path)
 }
+
+/// Build an EPUB from already extracted content and save to a file, without fetching anything.
+///
+/// Together with [`fetch::Fetcher`] and [`Extractor`] this lets each stage of the
+/// pipeline be run (or cached, or substituted) separately:
+///
+/// ```no_run
+/// use http_epub::extract::Extractor;
+/// use http_epub::fetch::Fetcher;
+/// use url::Url;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let url = Url::parse("https://example.com/article")?;
+///
+/// // 1. Fetch
+/// let fetched = Fetcher::new().fetch_content(&url)?;
+///
+/// // 2. Extract (or `extractor.process_html(&html, &url)` for HTML you already have)
+/// let extractor = Extractor::new();
+/// let extracted = extractor.process_fetched(fetched)?;
+///
+/// // 3. Build
+/// let path = http_epub::content_to_epub(&extracted, None)?;
+/// println!("Saved to {}", path.display());
+/// # Ok(())
+/// # }
+/// ```
+pub fn content_to_epub(
+    extracted: &ExtractedContent,
+    output_path: Option<&PathBuf>,
+) -> Result<PathBuf> {
+    epub::create_epub(extracted, output_path)
+}
+
+/// Convert a URL to EPUB format in memory, reusing an already configured extractor
+pub fn url_to_epub_bytes(
+    extractor: &Extractor,
+    url_str: &str,
+    epub_options: &EpubOptions,
+) -> Result<EpubBytes> {
+    let url = parse_input_url(url_str)?;
+    let extracted_content = extractor.process(&url)?;
+    let filename = epub::default_filename(&extracted_content);
+
+    let mut data = Vec::new();
+    epub::write_epub_with_options(&extracted_content, &mut data, epub_options)?;
+
+    Ok(EpubBytes { filename, data })
+}
+
+pub(crate) fn parse_input_url(url_str: &str) -> Result<Url> {
+    Url::parse(url_str)
+        .map_err(|e| anyhow::anyhow!("Failed to parse input URL '{}': {}", url_str, e))
+}
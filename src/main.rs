@@ -1,8 +1,8 @@
 use anyhow::Result;
+use std::time::Duration;
 
-// cli module is local to the binary
-mod cli;
-// epub, extract, fetch are part of the library (lib.rs) and accessed via http_epub::
+// cli, epub, extract, fetch are part of the library (lib.rs) and accessed via http_epub::
+use http_epub::cli;
 
 fn main() -> Result<()> {
     // Initialize tracing subscriber
@@ -11,11 +11,71 @@ fn main() -> Result<()> {
     // Parse command line arguments
     let args = cli::parse_args();
 
-    // Call the library function to handle the core logic.
-    // The crate name is 'http-epub', so in code it's 'http_epub'.
-    println!("Processing URL: {}", args.url);
-    let output_path = http_epub::url_to_epub(&args.url, args.output.as_ref())?;
+    let options = http_epub::Options {
+        fetch: http_epub::fetch::FetchOptions {
+            max_retries: args.max_retries,
+            max_retry_wait: Duration::from_secs(args.max_retry_wait),
+            max_redirects: args.max_redirects,
+            max_meta_refreshes: args.max_meta_refreshes,
+            image_allow_hosts: args.image_allow_host.clone(),
+            image_deny_hosts: args.image_deny_host.clone(),
+        },
+        extract: http_epub::extract::ExtractOptions {
+            flatten_images: args.flatten_images,
+            dump_raw_html: args.dump_raw_html.clone(),
+            overlay_patterns: if args.keep_overlays {
+                Some(Vec::new())
+            } else if args.overlay_pattern.is_empty() {
+                None
+            } else {
+                Some(args.overlay_pattern.clone())
+            },
+            strip_title_suffix: args.title_suffix_strip,
+        },
+        epub: http_epub::epub::EpubOptions {
+            no_cover: args.no_cover,
+            split_by_heading: args.split_by_heading,
+            identifier: args.identifier,
+        },
+    };
 
-    println!("EPUB successfully created at: {}", output_path.display());
+    if let Some(cli::Command::Serve { port, host }) = args.command {
+        return http_epub::server::serve(&format!("{host}:{port}"), &options);
+    }
+
+    let batch_options = http_epub::batch::BatchOptions {
+        since: args.since.map(|since| http_epub::batch::DateFilter {
+            since,
+            include_undated: !args.exclude_undated,
+        }),
+        jobs: args.jobs.into(),
+        state_file: args.state_file.clone(),
+    };
+
+    // clap requires at least one --url when no subcommand is given
+    let summary = http_epub::batch::run(&args.url, args.output.as_ref(), &options, &batch_options)?;
+
+    for (_, output_path) in &summary.converted {
+        println!("EPUB successfully created at: {}", output_path.display());
+    }
+    for (url, output_path) in &summary.already_converted {
+        println!(
+            "Already converted (per --state-file): {url} -> {}",
+            output_path.display()
+        );
+    }
+    for url in &summary.skipped {
+        println!("Skipped by --since: {url}");
+    }
+    for (url, e) in &summary.failed {
+        eprintln!("Failed to convert {url}: {e:#}");
+    }
+    if !summary.failed.is_empty() {
+        anyhow::bail!(
+            "{} of {} URLs failed to convert",
+            summary.failed.len(),
+            args.url.len()
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result, anyhow};
+use pdf_extract::{Document, Object, decode_text_string};
+use std::panic;
+use tracing::debug;
+
+/// Text and metadata pulled out of a PDF document
+pub struct PdfText {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub text: String,
+}
+
+/// Extracts the plain text and Info dictionary metadata from an in-memory PDF.
+/// Images and layout are not preserved.
+pub fn extract_pdf(data: &[u8]) -> Result<PdfText> {
+    let document = Document::load_mem(data).context("Failed to parse PDF document")?;
+    let title = info_string(&document, b"Title");
+    let author = info_string(&document, b"Author");
+
+    // pdf-extract panics instead of returning an error on many fonts and encodings
+    // it does not support, so contain the panic to this document
+    let text = panic::catch_unwind(|| pdf_extract::extract_text_from_mem(data))
+        .map_err(|payload| {
+            let reason = payload
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| payload.downcast_ref::<&str>().copied())
+                .unwrap_or("unsupported PDF content");
+            anyhow!("Failed to extract PDF text: {}", reason)
+        })?
+        .context("Failed to extract PDF text")?;
+    debug!(text_len = text.len(), title = ?title, "Extracted text from PDF.");
+
+    Ok(PdfText {
+        title,
+        author,
+        text,
+    })
+}
+
+/// Converts extracted PDF text into simple XHTML paragraphs, splitting on blank lines
+pub fn text_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for line in text.lines().chain(std::iter::once("")) {
+        let line = line.trim();
+        if line.is_empty() {
+            if !paragraph.is_empty() {
+                html.push_str("<p>");
+                html.push_str(&tera::escape_html(&paragraph.join(" ")));
+                html.push_str("</p>\n");
+                paragraph.clear();
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    html
+}
+
+fn info_string(document: &Document, key: &[u8]) -> Option<String> {
+    let info = document.trailer.get(b"Info").ok()?;
+    let (_, info) = document.dereference(info).ok()?;
+    let value = info.as_dict().ok()?.get(key).ok()?;
+    let (_, value) = document.dereference(value).ok()?;
+    match value {
+        Object::String(..) => decode_text_string(value)
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf_extract::content::{Content, Operation};
+    use pdf_extract::{Stream, dictionary};
+
+    // Builds a minimal one-page PDF with the given text and Info title
+    fn sample_pdf(text: &str, title: &str) -> Vec<u8> {
+        sample_pdf_with_font(
+            text,
+            title,
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Courier",
+            },
+        )
+    }
+
+    fn sample_pdf_with_font(text: &str, title: &str, font: pdf_extract::Dictionary) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(font);
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), 700.into()]),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal(title),
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_extract_pdf_text_and_title() {
+        let data = sample_pdf("Hello from a PDF", "Sample Paper");
+        let extracted = extract_pdf(&data).unwrap();
+
+        assert_eq!(extracted.title.as_deref(), Some("Sample Paper"));
+        assert_eq!(extracted.author, None);
+        assert!(extracted.text.contains("Hello from a PDF"));
+
+        let html = text_to_html(&extracted.text);
+        assert!(html.contains("<p>Hello from a PDF</p>"));
+    }
+
+    #[test]
+    fn test_unsupported_pdf_is_an_error() {
+        // pdf-extract panics on encodings it does not know
+        let data = sample_pdf_with_font(
+            "Unreadable",
+            "Odd Encoding",
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Courier",
+                "Encoding" => "BogusEncoding",
+            },
+        );
+
+        let error = extract_pdf(&data).err().expect("extraction should fail");
+        assert!(error.to_string().starts_with("Failed to extract PDF text"));
+    }
+}
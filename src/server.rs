@@ -0,0 +1,199 @@
+use crate::epub::EpubOptions;
+use crate::extract::Extractor;
+use crate::{Options, url_to_epub_bytes};
+use anyhow::{Result, anyhow};
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, warn};
+use url::Url;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Runs a blocking HTTP server that converts `GET /convert?url=...` requests to EPUBs.
+/// A single extractor, built from `options`, is shared by all requests.
+pub fn serve(addr: &str, options: &Options) -> Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+    info!(addr, "Listening for conversion requests on /convert");
+
+    let extractor = options.extractor();
+    run(&server, &extractor, &options.epub);
+    Ok(())
+}
+
+/// Answers requests until the server is shut down. Each request is handled in
+/// isolation: a failed or panicking conversion only affects its own response.
+fn run(server: &Server, extractor: &Extractor, epub_options: &EpubOptions) {
+    for request in server.incoming_requests() {
+        let response = handle_request(&request, extractor, epub_options);
+        if let Err(e) = request.respond(response) {
+            warn!(error = %e, "Failed to send response");
+        }
+    }
+}
+
+fn handle_request(
+    request: &Request,
+    extractor: &Extractor,
+    epub_options: &EpubOptions,
+) -> HttpResponse {
+    // Request URLs are relative; resolve against a dummy base to parse path and query
+    let request_url = match Url::parse("http://localhost").and_then(|base| base.join(request.url()))
+    {
+        Ok(url) => url,
+        Err(e) => return text_response(400, &format!("Invalid request URL: {e}")),
+    };
+
+    if request_url.path() != "/convert" {
+        return text_response(404, "Not found; use GET /convert?url=<article URL>");
+    }
+    if *request.method() != Method::Get {
+        return text_response(405, "Method not allowed; use GET");
+    }
+
+    let Some(target) = request_url
+        .query_pairs()
+        .find(|(key, _)| key == "url")
+        .map(|(_, value)| value.into_owned())
+    else {
+        return text_response(400, "Missing required 'url' query parameter");
+    };
+    match Url::parse(&target) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => return text_response(400, &format!("Invalid article URL: {target}")),
+    }
+
+    info!(url = target, "Converting requested URL");
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        url_to_epub_bytes(extractor, &target, epub_options)
+    }));
+    match result {
+        Ok(Ok(epub)) => {
+            let disposition = content_disposition(&epub.filename);
+            Response::from_data(epub.data)
+                .with_header(header("Content-Type", "application/epub+zip"))
+                .with_header(header("Content-Disposition", &disposition))
+        }
+        Ok(Err(e)) => {
+            warn!(url = target, error = %e, "Conversion failed");
+            text_response(500, &format!("Failed to convert {target}: {e:#}"))
+        }
+        Err(_) => {
+            warn!(url = target, "Conversion panicked");
+            text_response(500, &format!("Failed to convert {target}: internal error"))
+        }
+    }
+}
+
+fn text_response(status: u16, message: &str) -> HttpResponse {
+    Response::from_string(message)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "text/plain; charset=utf-8"))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("header is valid ASCII")
+}
+
+/// Builds an attachment disposition with an ASCII fallback name and an RFC 5987
+/// encoded name for titles outside of ASCII.
+fn content_disposition(filename: &str) -> String {
+    let ascii_fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{ascii_fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // Starts a server on an ephemeral port and returns its base URL
+    fn start_server() -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || run(&server, &Extractor::new(), &EpubOptions::default()));
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("Café \"Notes\".epub"),
+            "attachment; filename=\"Caf_ _Notes_.epub\"; filename*=UTF-8''Caf%C3%A9%20%22Notes%22.epub"
+        );
+    }
+
+    #[test]
+    fn test_convert_endpoint() {
+        let mut upstream = mockito::Server::new();
+        let paragraph = "<p>Serving EPUBs over HTTP lets other tools request conversions without shelling out to the command-line binary.</p>";
+        upstream
+            .mock("GET", "/article")
+            .with_header("content-type", "text/html")
+            .with_body(format!(
+                "<html><head><title>Served Article</title></head><body><article><h1>Served Article</h1>{}</article></body></html>",
+                paragraph.repeat(5)
+            ))
+            .create();
+        let base = start_server();
+        let client = reqwest::blocking::Client::new();
+
+        let missing = client.get(format!("{base}/convert")).send().unwrap();
+        assert_eq!(missing.status(), 400);
+
+        let invalid = client
+            .get(format!("{base}/convert"))
+            .query(&[("url", "not a url")])
+            .send()
+            .unwrap();
+        assert_eq!(invalid.status(), 400);
+
+        let response = client
+            .get(format!("{base}/convert"))
+            .query(&[("url", format!("{}/article", upstream.url()))])
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers()["content-type"].to_str().unwrap(),
+            "application/epub+zip"
+        );
+        assert!(
+            response.headers()["content-disposition"]
+                .to_str()
+                .unwrap()
+                .contains("Served Article.epub")
+        );
+        let body = response.bytes().unwrap();
+        assert!(body.starts_with(b"PK"));
+
+        // A failed conversion is reported without taking the server down
+        let failed = client
+            .get(format!("{base}/convert"))
+            .query(&[("url", "http://127.0.0.1:1/unreachable")])
+            .send()
+            .unwrap();
+        assert_eq!(failed.status(), 500);
+        let still_up = client.get(format!("{base}/convert")).send().unwrap();
+        assert_eq!(still_up.status(), 400);
+    }
+}